@@ -0,0 +1,345 @@
+//! Structural checks for edited dungeon data.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+};
+
+use crate::{graph::is_trapdoor, terrain, Dungeon, DungeonBlock};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+pub struct Finding {
+    pub severity: Severity,
+    /// Level index, 0 is the topmost level.
+    pub level: usize,
+    /// Block position on the 8x8 floor.
+    pub block: (usize, usize),
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(
+            f,
+            "level {} ({}, {}): {}: {}",
+            self.level + 1,
+            self.block.0,
+            self.block.1,
+            severity,
+            self.message
+        )
+    }
+}
+
+/// Check a dungeon for broken ladders, rooms and floor layouts.
+pub fn check(dungeon: &Dungeon) -> Vec<Finding> {
+    let mut ret = Vec::new();
+    ladders(dungeon, &mut ret);
+    rooms(dungeon, &mut ret);
+    reachability(dungeon, &mut ret);
+    ret
+}
+
+fn block(dungeon: &Dungeon, z: usize, x: usize, y: usize) -> DungeonBlock {
+    dungeon.floors[z].0[y % 8][x % 8]
+}
+
+fn ladders(dungeon: &Dungeon, findings: &mut Vec<Finding>) {
+    for z in 0..8 {
+        for y in 0..8 {
+            for x in 0..8 {
+                let b = block(dungeon, z, x, y);
+                let mut error = |message: &str| {
+                    findings.push(Finding {
+                        severity: Severity::Error,
+                        level: z,
+                        block: (x, y),
+                        message: message.into(),
+                    })
+                };
+
+                // Up ladders on the first level lead out of the dungeon.
+                if b.ladder_up()
+                    && z > 0
+                    && !block(dungeon, z - 1, x, y).ladder_down()
+                {
+                    error("up ladder without a down ladder on the level above");
+                }
+                if b.ladder_down()
+                    && (z == 7 || !block(dungeon, z + 1, x, y).ladder_up())
+                {
+                    error(
+                        "down ladder without an up ladder on the level below",
+                    );
+                }
+                if let DungeonBlock::Unknown(b) = b {
+                    findings.push(Finding {
+                        severity: Severity::Warning,
+                        level: z,
+                        block: (x, y),
+                        message: format!("unknown block value {:#04x}", b),
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn rooms(dungeon: &Dungeon, findings: &mut Vec<Finding>) {
+    // Room contents only need to be reported once.
+    let mut seen = HashSet::new();
+
+    for z in 0..8 {
+        for y in 0..8 {
+            for x in 0..8 {
+                let DungeonBlock::Room { index, .. } = block(dungeon, z, x, y)
+                else {
+                    continue;
+                };
+                let room = &dungeon.rooms[index as usize];
+                let mut error = |message: String| {
+                    findings.push(Finding {
+                        severity: Severity::Error,
+                        level: z,
+                        block: (x, y),
+                        message,
+                    })
+                };
+
+                if room.area.iter().flatten().all(|&t| t == 0) {
+                    if seen.insert(index) {
+                        error(format!("room {} is empty", index));
+                    }
+                    continue;
+                }
+
+                let outside = |p: &[u8; 2]| p[0] >= 11 || p[1] >= 11;
+                if seen.insert(index) {
+                    let mut monsters: Vec<_> = room
+                        .monster_slots
+                        .iter()
+                        .filter(|m| m.tile != 0 && outside(&m.pos))
+                        .map(|m| m.pos)
+                        .collect();
+                    monsters.sort();
+                    monsters.dedup();
+                    for p in monsters {
+                        error(format!(
+                            "monster of room {} at ({}, {}) is outside the room",
+                            index, p[0], p[1]
+                        ));
+                    }
+
+                    let mut triggers: Vec<_> = room.triggers().collect();
+                    triggers.sort_by_key(|t| t.pos);
+                    for t in triggers {
+                        let p = t.pos;
+                        if outside(&p) {
+                            error(format!(
+                                "trigger of room {} at ({}, {}) is outside the room",
+                                index, p[0], p[1]
+                            ));
+                        }
+                        let mut changes = t.changes.to_vec();
+                        changes.dedup();
+                        for c in changes.iter().filter(|c| outside(c)) {
+                            error(format!(
+                                "trigger of room {} at ({}, {}) changes ({}, {}) \
+                                 outside the room",
+                                index, p[0], p[1], c[0], c[1]
+                            ));
+                        }
+                    }
+                }
+
+                // Entrances from open neighboring blocks.
+                for (side, (dx, dy), starts) in [
+                    ("east", (1, 0), &room.player_east),
+                    ("west", (7, 0), &room.player_west),
+                    ("south", (0, 1), &room.player_south),
+                    ("north", (0, 7), &room.player_north),
+                ] {
+                    if !block(dungeon, z, x + dx, y + dy).is_open() {
+                        continue;
+                    }
+                    if starts.iter().all(|p| *p == [0, 0]) {
+                        error(format!(
+                            "room {} has no entrance from the {}",
+                            index, side
+                        ));
+                        continue;
+                    }
+                    for p in starts {
+                        if outside(p) {
+                            error(format!(
+                                "party start ({}, {}) from the {} is outside room {}",
+                                p[0], p[1], side, index
+                            ));
+                            continue;
+                        }
+                        let terrain = terrain::classify(
+                            room.area[p[1] as usize][p[0] as usize] as usize,
+                        );
+                        if !terrain.is_passable() {
+                            error(format!(
+                                "party start ({}, {}) from the {} in room {} is \
+                                 blocked by {:?}",
+                                p[0], p[1], side, index, terrain
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Whether the party can walk into a block. Unknown blocks are not assumed
+/// to be passable.
+fn passable(b: DungeonBlock) -> bool {
+    b.is_open() && !matches!(b, DungeonBlock::Unknown(_))
+}
+
+/// Blocks reachable in one move from a block, including falling through a
+/// trapdoor to the level below.
+fn moves(
+    dungeon: &Dungeon,
+    (z, x, y): (usize, usize, usize),
+) -> Vec<(usize, usize, usize)> {
+    let mut ret = dungeon.neighbors((z, x, y), passable);
+    if z < 7
+        && is_trapdoor(block(dungeon, z, x, y))
+        && passable(block(dungeon, z + 1, x, y))
+    {
+        ret.push((z + 1, x, y));
+    }
+    ret
+}
+
+fn reachability(dungeon: &Dungeon, findings: &mut Vec<Finding>) {
+    // The dungeon is entered through the up ladders of the first level.
+    let exits: Vec<_> = (0..8)
+        .flat_map(|y| (0..8).map(move |x| (0, x, y)))
+        .filter(|&(z, x, y)| block(dungeon, z, x, y).ladder_up())
+        .collect();
+    if exits.is_empty() {
+        findings.push(Finding {
+            severity: Severity::Error,
+            level: 0,
+            block: (0, 0),
+            message: "no up ladder to leave the dungeon".into(),
+        });
+        return;
+    }
+
+    let mut reached: HashSet<_> = exits.iter().copied().collect();
+    let mut edge: VecDeque<_> = exits.into_iter().collect();
+    while let Some(pos) = edge.pop_front() {
+        for n in moves(dungeon, pos) {
+            if reached.insert(n) {
+                edge.push_back(n);
+            }
+        }
+    }
+
+    // Report each unreachable region of a level once.
+    for z in 0..8 {
+        for y in 0..8 {
+            for x in 0..8 {
+                if reached.contains(&(z, x, y))
+                    || !passable(block(dungeon, z, x, y))
+                {
+                    continue;
+                }
+
+                let mut size = 0;
+                let mut edge = VecDeque::from([(z, x, y)]);
+                reached.insert((z, x, y));
+                while let Some(pos) = edge.pop_front() {
+                    size += 1;
+                    for n in dungeon.neighbors(pos, passable) {
+                        if n.0 == z && reached.insert(n) {
+                            edge.push_back(n);
+                        }
+                    }
+                }
+
+                findings.push(Finding {
+                    severity: Severity::Warning,
+                    level: z,
+                    block: (x, y),
+                    message: format!("unreachable region of {} blocks", size),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        combat_map::CombatMap, dungeon_block::TrapKind, DungeonFloor,
+        DungeonKind,
+    };
+
+    #[test]
+    fn trapdoor_regions() {
+        let wall = DungeonBlock::Wall {
+            secondary: false,
+            text: false,
+            extra: 0,
+        };
+        let mut floors = vec![DungeonFloor([[wall; 8]; 8]); 8];
+        // Entrance and a trapdoor on the first level, with a region on the
+        // second level that only the trapdoor leads to.
+        floors[0].0[0][0] = DungeonBlock::UpLadder {
+            upper_trap: false,
+            extra: 0,
+        };
+        floors[0].0[0][1] = DungeonBlock::Trap {
+            kind: TrapKind::Visible,
+            upper: false,
+        };
+        floors[0].0[0][2] = DungeonBlock::Unknown(0x90);
+        floors[1].0[0][1] = DungeonBlock::Corridor;
+        floors[1].0[0][2] = DungeonBlock::Corridor;
+        let mut dungeon = Dungeon {
+            name: "Doom".into(),
+            kind: DungeonKind::Cave,
+            entrance: None,
+            floors,
+            rooms: vec![CombatMap::default(); 16],
+            cleared_rooms: 0,
+            cbt_tail: Vec::new(),
+        };
+
+        let unreachable = |dungeon: &Dungeon| {
+            let mut findings = Vec::new();
+            reachability(dungeon, &mut findings);
+            findings
+                .iter()
+                .map(|f| (f.level, f.block, f.message.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert!(unreachable(&dungeon).is_empty());
+
+        dungeon.floors[0].0[0][1] = DungeonBlock::Trap {
+            kind: TrapKind::Bomb,
+            upper: false,
+        };
+        assert_eq!(
+            unreachable(&dungeon),
+            vec![(1, (1, 0), "unreachable region of 2 blocks".to_string())]
+        );
+    }
+}
@@ -2,7 +2,7 @@ use std::{collections::HashMap, fmt};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Default, Serialize, Deserialize, Debug)]
 pub(crate) struct CombatMapRaw {
     // The native structure is messy,
     // <https://wiki.ultimacodex.com/wiki/Ultima_V_internal_formats>.
@@ -60,7 +60,25 @@ pub(crate) struct CombatMapRaw {
     pad_10: [u8; 5],
 }
 
-#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+/// Monster slot of a combat map.
+#[derive(Copy, Clone, Default, Debug, Eq, PartialEq)]
+pub struct Monster {
+    /// Monster tile, 0 for an empty slot.
+    pub tile: usize,
+    pub pos: [u8; 2],
+}
+
+/// Trigger slot of a combat map. Stepping on `pos` changes the tiles at
+/// `changes` into `tile`.
+#[derive(Copy, Clone, Default, Debug, Eq, PartialEq)]
+pub struct Trigger {
+    /// New tile, 0 for an empty slot.
+    pub tile: u8,
+    pub pos: [u8; 2],
+    pub changes: [[u8; 2]; 2],
+}
+
+#[derive(Clone, Default, Debug, PartialEq, Deserialize)]
 #[serde(from = "CombatMapRaw")]
 pub struct CombatMap {
     pub area: [[u8; 11]; 11],
@@ -69,9 +87,27 @@ pub struct CombatMap {
     pub player_south: [[u8; 2]; 6],
     pub player_north: [[u8; 2]; 6],
 
-    pub monsters: HashMap<[u8; 2], usize>,
+    /// Monster slots, empty slots keep the position they were read with.
+    pub monster_slots: [Monster; 16],
+    /// Trigger slots, empty slots keep the positions they were read with.
+    pub trigger_slots: [Trigger; 8],
 
-    pub triggers: HashMap<[u8; 2], HashMap<[u8; 2], u8>>,
+    /// Unused bytes after the data of each row, empty for all zeros.
+    pub padding: Vec<u8>,
+}
+
+/// Number of unused bytes in a DUNGEON.CBT record.
+const PADDING: usize = 13 + 4 * 9 + 6 * 5;
+
+fn split_coords<const N: usize>(
+    coords: &[[u8; 2]; N],
+    xs: &mut [u8; N],
+    ys: &mut [u8; N],
+) {
+    for i in 0..N {
+        xs[i] = coords[i][0];
+        ys[i] = coords[i][1];
+    }
 }
 
 fn merge_coords<const N: usize>(xs: &[u8; N], ys: &[u8; N]) -> [[u8; 2]; N] {
@@ -114,28 +150,206 @@ impl From<CombatMapRaw> for CombatMap {
         ret.player_north =
             merge_coords(&data.player_x_north, &data.player_y_north);
 
-        for (i, &m) in data.monsters.iter().enumerate() {
-            if m != 0 {
-                let p = [data.monsters_x[i], data.monsters_y[i]];
-                //debug_assert!(!ret.monsters.contains_key(&p));
-                ret.monsters.insert(p, m as usize + 256);
-            }
+        for (i, slot) in ret.monster_slots.iter_mut().enumerate() {
+            let m = data.monsters[i];
+            *slot = Monster {
+                tile: if m == 0 { 0 } else { m as usize + 256 },
+                pos: [data.monsters_x[i], data.monsters_y[i]],
+            };
+        }
+
+        for (i, slot) in ret.trigger_slots.iter_mut().enumerate() {
+            *slot = Trigger {
+                tile: data.new_tiles[i],
+                pos: [data.trigger_x[i], data.trigger_y[i]],
+                changes: [
+                    [data.change_0_x[i], data.change_0_y[i]],
+                    [data.change_1_x[i], data.change_1_y[i]],
+                ],
+            };
+        }
+
+        let padding = [
+            &data.pad_0[..],
+            &data.pad_1,
+            &data.pad_2,
+            &data.pad_3,
+            &data.pad_4,
+            &data.pad_5,
+            &data.pad_6,
+            &data.pad_7,
+            &data.pad_8,
+            &data.pad_9,
+            &data.pad_10,
+        ]
+        .concat();
+        if padding.iter().any(|&b| b != 0) {
+            ret.padding = padding;
+        }
+
+        ret
+    }
+}
+
+impl CombatMap {
+    /// Encode the map into the 352 bytes of a DUNGEON.CBT record.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        Ok(bincode::serialize(&CombatMapRaw::try_from(self)?).unwrap())
+    }
+
+    /// Monster tile at a position. When slots share a position the last one
+    /// is the one in effect.
+    pub fn monster_at(&self, pos: [u8; 2]) -> Option<usize> {
+        self.monster_slots
+            .iter()
+            .rev()
+            .find(|m| m.tile != 0 && m.pos == pos)
+            .map(|m| m.tile)
+    }
+
+    /// Monster tiles by position, as they are in effect.
+    pub fn monsters(&self) -> HashMap<[u8; 2], usize> {
+        self.monster_slots
+            .iter()
+            .filter(|m| m.tile != 0)
+            .map(|m| (m.pos, m.tile))
+            .collect()
+    }
+
+    /// Triggers of the occupied trigger slots.
+    pub fn triggers(&self) -> impl Iterator<Item = &Trigger> {
+        self.trigger_slots.iter().filter(|t| t.tile != 0)
+    }
+
+    /// Replace the monsters with new ones, in slot order.
+    pub fn set_monsters(
+        &mut self,
+        monsters: impl IntoIterator<Item = ([u8; 2], usize)>,
+    ) -> Result<(), String> {
+        let mut slots = [Monster::default(); 16];
+        for (i, (pos, tile)) in monsters.into_iter().enumerate() {
+            let slot = slots.get_mut(i).ok_or("More than 16 monsters")?;
+            *slot = Monster { tile, pos };
+        }
+        self.monster_slots = slots;
+        Ok(())
+    }
+
+    /// Replace the triggers with new ones, in slot order.
+    pub fn set_triggers(
+        &mut self,
+        triggers: impl IntoIterator<Item = Trigger>,
+    ) -> Result<(), String> {
+        let mut slots = [Trigger::default(); 8];
+        for (i, trigger) in triggers.into_iter().enumerate() {
+            *slots.get_mut(i).ok_or("More than 8 triggers")? = trigger;
+        }
+        self.trigger_slots = slots;
+        Ok(())
+    }
+}
+
+impl TryFrom<&CombatMap> for CombatMapRaw {
+    type Error = String;
+
+    /// Convert back into the native structure.
+    fn try_from(map: &CombatMap) -> Result<Self, Self::Error> {
+        let mut data = CombatMapRaw::default();
+
+        for (y, row) in [
+            &mut data.row_0,
+            &mut data.row_1,
+            &mut data.row_2,
+            &mut data.row_3,
+            &mut data.row_4,
+            &mut data.row_5,
+            &mut data.row_6,
+            &mut data.row_7,
+            &mut data.row_8,
+            &mut data.row_9,
+            &mut data.row_10,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            *row = map.area[y];
         }
 
-        for (i, &t) in data.new_tiles.iter().enumerate() {
-            if t != 0 {
-                let trigger = [data.trigger_x[i], data.trigger_y[i]];
-                //debug_assert!(!ret.triggers.contains_key(&trigger));
+        split_coords(
+            &map.player_east,
+            &mut data.player_x_east,
+            &mut data.player_y_east,
+        );
+        split_coords(
+            &map.player_west,
+            &mut data.player_x_west,
+            &mut data.player_y_west,
+        );
+        split_coords(
+            &map.player_south,
+            &mut data.player_x_south,
+            &mut data.player_y_south,
+        );
+        split_coords(
+            &map.player_north,
+            &mut data.player_x_north,
+            &mut data.player_y_north,
+        );
 
-                let p1 = [data.change_0_x[i], data.change_0_y[i]];
-                let p2 = [data.change_1_x[i], data.change_1_y[i]];
+        for (i, m) in map.monster_slots.iter().enumerate() {
+            data.monsters[i] = match m.tile {
+                0 => 0,
+                257..512 => (m.tile - 256) as u8,
+                t => {
+                    return Err(format!(
+                        "Bad monster tile {} at {:?}",
+                        t, m.pos
+                    ))
+                }
+            };
+            data.monsters_x[i] = m.pos[0];
+            data.monsters_y[i] = m.pos[1];
+        }
 
-                ret.triggers
-                    .insert(trigger, [(p1, t), (p2, t)].into_iter().collect());
+        for (i, t) in map.trigger_slots.iter().enumerate() {
+            data.new_tiles[i] = t.tile;
+            data.trigger_x[i] = t.pos[0];
+            data.trigger_y[i] = t.pos[1];
+            data.change_0_x[i] = t.changes[0][0];
+            data.change_0_y[i] = t.changes[0][1];
+            data.change_1_x[i] = t.changes[1][0];
+            data.change_1_y[i] = t.changes[1][1];
+        }
+
+        if !map.padding.is_empty() {
+            if map.padding.len() != PADDING {
+                return Err(format!(
+                    "{} bytes of padding instead of {}",
+                    map.padding.len(),
+                    PADDING
+                ));
+            }
+            let mut rest = &map.padding[..];
+            for pad in [
+                &mut data.pad_0[..],
+                &mut data.pad_1,
+                &mut data.pad_2,
+                &mut data.pad_3,
+                &mut data.pad_4,
+                &mut data.pad_5,
+                &mut data.pad_6,
+                &mut data.pad_7,
+                &mut data.pad_8,
+                &mut data.pad_9,
+                &mut data.pad_10,
+            ] {
+                let (head, tail) = rest.split_at(pad.len());
+                pad.copy_from_slice(head);
+                rest = tail;
             }
         }
 
-        ret
+        Ok(data)
     }
 }
 
@@ -143,7 +357,8 @@ impl fmt::Display for CombatMap {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for y in 0..11 {
             for x in 0..11 {
-                let terrain = crate::TERRAIN[self.area[y][x] as usize];
+                let terrain =
+                    crate::terrain::classify(self.area[y][x] as usize);
                 let c: char = terrain.into();
                 write!(f, "{}", c)?;
             }
@@ -152,3 +367,60 @@ impl fmt::Display for CombatMap {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Record filled with arbitrary bytes.
+    fn noise(seed: u32) -> Vec<u8> {
+        let mut x = seed;
+        (0..std::mem::size_of::<CombatMapRaw>())
+            .map(|_| {
+                x = x.wrapping_mul(1103515245).wrapping_add(12345);
+                (x >> 16) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        for seed in 0..64 {
+            let data = noise(seed);
+            let map: CombatMap = bincode::deserialize(&data).unwrap();
+            assert_eq!(map.to_bytes().unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn built_round_trip() {
+        // A map that wasn't read from any data.
+        let mut map = CombatMap::default();
+        map.area[3][4] = 0x4c;
+        map.player_north[2] = [5, 10];
+        map.set_monsters([([2, 3], 300), ([2, 3], 0x1ec), ([9, 1], 400)])
+            .unwrap();
+        map.set_triggers([Trigger {
+            tile: 0x4e,
+            pos: [5, 5],
+            changes: [[1, 1], [1, 2]],
+        }])
+        .unwrap();
+        map.monster_slots[15].pos = [7, 7];
+
+        let bytes = map.to_bytes().unwrap();
+        assert_eq!(bytes.len(), std::mem::size_of::<CombatMapRaw>());
+        let read: CombatMap = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(read, map);
+        assert_eq!(read.monster_at([2, 3]), Some(0x1ec));
+        assert_eq!(read.monsters().len(), 2);
+
+        map.padding = vec![1; PADDING];
+        let read: CombatMap =
+            bincode::deserialize(&map.to_bytes().unwrap()).unwrap();
+        assert_eq!(read, map);
+
+        map.set_monsters([([0, 0], 256)]).unwrap();
+        assert!(map.to_bytes().is_err());
+    }
+}
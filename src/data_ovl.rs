@@ -0,0 +1,56 @@
+//! Tables read from the DATA.OVL overlay file.
+
+use std::fs;
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref DATA_OVL: Option<Vec<u8>> =
+        fs::read(crate::U5_PATH.join("DATA.OVL")).ok();
+}
+
+fn data() -> &'static [u8] {
+    DATA_OVL.as_deref().expect("DATA.OVL not found")
+}
+
+/// Chunk indices into BRIT.DAT for the 16x16 chunks of the Britannia map.
+///
+/// Value 0xFF means the chunk is not stored and consists of only water.
+pub fn brit_chunks() -> &'static [u8] {
+    &data()[0x3886..0x3886 + 0x100]
+}
+
+/// First map index of each of the eight locations stored in a settlement
+/// data file.
+pub fn settlement_map_starts(file: &str) -> [u8; 8] {
+    let offset = match file {
+        "TOWNE.DAT" => 0x1e2a,
+        "DWELLING.DAT" => 0x1e32,
+        "CASTLE.DAT" => 0x1e3a,
+        "KEEP.DAT" => 0x1e42,
+        _ => panic!("Unknown settlement file {}", file),
+    };
+    data()[offset..offset + 8].try_into().unwrap()
+}
+
+/// Names of the eight dungeons.
+pub fn dungeon_names() -> Option<Vec<String>> {
+    let names: Vec<String> = DATA_OVL.as_ref()?[0xb5e..0xb5e + 0x3a]
+        .split(|&c| c == 0)
+        .take(8)
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect();
+    (names.len() == 8 && names.iter().all(|s| !s.is_empty())).then_some(names)
+}
+
+/// Overworld tiles of the eight dungeon entrances once they've been opened.
+pub fn dungeon_sprites() -> Option<[u8; 8]> {
+    DATA_OVL.as_ref()?[0x4513..0x4513 + 8].try_into().ok()
+}
+
+/// Overworld position of a settlement or dungeon location.
+pub fn location_pos(location: u8) -> Option<(u8, u8)> {
+    let data = DATA_OVL.as_ref()?;
+    let i = (location as usize).checked_sub(1).filter(|&i| i < 0x28)?;
+    Some((data[0x1e9a + i], data[0x1ec2 + i]))
+}
@@ -0,0 +1,107 @@
+//! Differences between two versions of the dungeon data.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    combat_map::{CombatMap, Trigger},
+    monsters, Dungeon,
+};
+
+/// Changed floor blocks.
+pub fn blocks(base: &Dungeon, other: &Dungeon) -> Vec<String> {
+    let mut ret = Vec::new();
+    for z in 0..8 {
+        for y in 0..8 {
+            for x in 0..8 {
+                let (a, b) = (base.floors[z].0[y][x], other.floors[z].0[y][x]);
+                if a != b {
+                    ret.push(format!(
+                        "level {} ({}, {}): {} -> {}",
+                        z + 1,
+                        x,
+                        y,
+                        a.name(),
+                        b.name()
+                    ));
+                }
+            }
+        }
+    }
+    ret
+}
+
+/// Triggers of a room by position.
+fn triggers(room: &CombatMap) -> BTreeMap<[u8; 2], Vec<Trigger>> {
+    let mut ret: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for t in room.triggers() {
+        ret.entry(t.pos).or_default().push(*t);
+    }
+    ret
+}
+
+/// Changed combat room layouts, monsters, triggers and party starts.
+pub fn rooms(base: &Dungeon, other: &Dungeon) -> Vec<String> {
+    let mut ret = Vec::new();
+    for (i, (a, b)) in base.rooms.iter().zip(&other.rooms).enumerate() {
+        let mut change = |msg: String| ret.push(format!("room {}: {}", i, msg));
+
+        let tiles = (0..11)
+            .flat_map(|y| (0..11).map(move |x| (x, y)))
+            .filter(|&(x, y)| a.area[y][x] != b.area[y][x])
+            .count();
+        if tiles > 0 {
+            change(format!("{} tiles changed", tiles));
+        }
+
+        let (ma, mb) = (a.monsters(), b.monsters());
+        let positions: BTreeSet<_> = ma.keys().chain(mb.keys()).collect();
+        for p in positions {
+            match (ma.get(p), mb.get(p)) {
+                (Some(m), None) => change(format!(
+                    "{} at ({}, {}) removed",
+                    monsters::name(*m),
+                    p[0],
+                    p[1]
+                )),
+                (None, Some(m)) => change(format!(
+                    "{} at ({}, {}) added",
+                    monsters::name(*m),
+                    p[0],
+                    p[1]
+                )),
+                (Some(m1), Some(m2)) if m1 != m2 => change(format!(
+                    "{} at ({}, {}) changed to {}",
+                    monsters::name(*m1),
+                    p[0],
+                    p[1],
+                    monsters::name(*m2)
+                )),
+                _ => {}
+            }
+        }
+
+        let (ta, tb) = (triggers(a), triggers(b));
+        let positions: BTreeSet<_> = ta.keys().chain(tb.keys()).collect();
+        for p in positions {
+            let what = match (ta.get(p), tb.get(p)) {
+                (Some(_), None) => "removed",
+                (None, Some(_)) => "added",
+                (Some(t1), Some(t2)) if t1 != t2 => "changed",
+                _ => continue,
+            };
+            change(format!("trigger at ({}, {}) {}", p[0], p[1], what));
+        }
+
+        for (side, s1, s2) in [
+            ("east", &a.player_east, &b.player_east),
+            ("west", &a.player_west, &b.player_west),
+            ("south", &a.player_south, &b.player_south),
+            ("north", &a.player_north, &b.player_north),
+        ] {
+            if s1 != s2 {
+                change(format!("party starts from the {} changed", side));
+            }
+        }
+    }
+    ret
+}
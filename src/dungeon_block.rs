@@ -0,0 +1,499 @@
+//! Dungeon floor blocks from DUNGEON.DAT.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Color::{self, *};
+
+/// One of the 8x8 blocks of a dungeon level.
+///
+/// Every byte of DUNGEON.DAT decodes into a block that encodes back into the
+/// same byte. Blocks are typed by the high nibble, low bits with no known
+/// meaning are kept in `extra` fields. Bytes without a known type are kept as
+/// `Unknown`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(from = "u8", into = "u8")]
+pub enum DungeonBlock {
+    Corridor,
+    UpLadder {
+        upper_trap: bool,
+        extra: u8,
+    },
+    DownLadder {
+        upper_trap: bool,
+        extra: u8,
+    },
+    UpDownLadder {
+        upper_trap: bool,
+        extra: u8,
+    },
+
+    Chest {
+        trap: u8,
+        poisoned: bool,
+        upper_trap: bool,
+    },
+    Fountain(FountainEffect),
+    Trap {
+        kind: TrapKind,
+        upper: bool,
+    },
+
+    OpenChest,
+
+    Field(FieldKind),
+
+    /// Wall, secondary walls use a different high nibble. Text walls have a
+    /// message.
+    Wall {
+        secondary: bool,
+        text: bool,
+        extra: u8,
+    },
+    SecretDoor {
+        extra: u8,
+    },
+    Door {
+        extra: u8,
+    },
+
+    /// Combat room, cleared rooms have had their monsters killed.
+    Room {
+        index: u8,
+        cleared: bool,
+    },
+
+    Unknown(u8),
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum FountainEffect {
+    CurePoison,
+    Heal,
+    Poison,
+    /// Bad taste and damage, with the original value.
+    Damage(u8),
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TrapKind {
+    Visible,
+    Bomb,
+    Invisible,
+    /// Undocumented value.
+    Other(u8),
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum FieldKind {
+    Poison,
+    Sleep,
+    Fire,
+    Energy,
+    /// Undocumented value.
+    Other(u8),
+}
+
+impl FieldKind {
+    /// Index of the field among the four field tiles.
+    pub fn tile_offset(self) -> usize {
+        u8::from(self) as usize & 0x3
+    }
+}
+
+impl From<u8> for FieldKind {
+    fn from(b: u8) -> Self {
+        match b {
+            0 => FieldKind::Poison,
+            1 => FieldKind::Sleep,
+            2 => FieldKind::Fire,
+            3 => FieldKind::Energy,
+            b => FieldKind::Other(b),
+        }
+    }
+}
+
+impl From<FieldKind> for u8 {
+    fn from(kind: FieldKind) -> u8 {
+        match kind {
+            FieldKind::Poison => 0,
+            FieldKind::Sleep => 1,
+            FieldKind::Fire => 2,
+            FieldKind::Energy => 3,
+            FieldKind::Other(b) => b,
+        }
+    }
+}
+
+impl DungeonBlock {
+    /// Whether there's a ladder up from the block.
+    pub fn ladder_up(self) -> bool {
+        matches!(
+            self,
+            DungeonBlock::UpLadder { .. } | DungeonBlock::UpDownLadder { .. }
+        )
+    }
+
+    /// Whether there's a ladder down from the block.
+    pub fn ladder_down(self) -> bool {
+        matches!(
+            self,
+            DungeonBlock::DownLadder { .. } | DungeonBlock::UpDownLadder { .. }
+        )
+    }
+
+    /// Whether the party can enter the block.
+    pub fn is_open(self) -> bool {
+        !matches!(self, DungeonBlock::Wall { .. })
+    }
+
+    /// Kind and subtype of the block in words, eg. "chest, trapped".
+    ///
+    /// Room indices are left out.
+    pub fn describe(self) -> String {
+        use DungeonBlock::*;
+
+        let (kind, mut flags) = match self {
+            Corridor => ("corridor", vec![]),
+            UpLadder { upper_trap, extra } => (
+                "up ladder",
+                vec![("upper trap", upper_trap), ("unknown bits", extra != 0)],
+            ),
+            DownLadder { upper_trap, extra } => (
+                "down ladder",
+                vec![("upper trap", upper_trap), ("unknown bits", extra != 0)],
+            ),
+            UpDownLadder { upper_trap, extra } => (
+                "up and down ladder",
+                vec![("upper trap", upper_trap), ("unknown bits", extra != 0)],
+            ),
+            Chest {
+                trap,
+                poisoned,
+                upper_trap,
+            } => (
+                "chest",
+                vec![
+                    ("trapped", trap != 0),
+                    ("poisoned", poisoned),
+                    ("upper trap", upper_trap),
+                ],
+            ),
+            Fountain(effect) => (
+                match effect {
+                    FountainEffect::CurePoison => "fountain, cure poison",
+                    FountainEffect::Heal => "fountain, heal",
+                    FountainEffect::Poison => "fountain, poison",
+                    FountainEffect::Damage(_) => "fountain, bad taste",
+                },
+                vec![],
+            ),
+            Trap { kind, upper } => (
+                match kind {
+                    TrapKind::Visible => "trap, visible",
+                    TrapKind::Bomb => "trap, bomb",
+                    TrapKind::Invisible => "trap, invisible",
+                    TrapKind::Other(_) => "trap, unknown",
+                },
+                vec![("upper", upper)],
+            ),
+            OpenChest => ("open chest", vec![]),
+            Field(kind) => (
+                match kind {
+                    FieldKind::Poison => "field, poison",
+                    FieldKind::Sleep => "field, sleep",
+                    FieldKind::Fire => "field, fire",
+                    FieldKind::Energy => "field, energy",
+                    FieldKind::Other(_) => "field, unknown",
+                },
+                vec![],
+            ),
+            Wall {
+                secondary,
+                text,
+                extra,
+            } => (
+                "wall",
+                vec![
+                    ("secondary", secondary),
+                    ("text", text),
+                    ("unknown bits", extra != 0),
+                ],
+            ),
+            SecretDoor { extra } => {
+                ("secret door", vec![("unknown bits", extra != 0)])
+            }
+            Door { extra } => ("door", vec![("unknown bits", extra != 0)]),
+            Room { cleared, .. } => ("room", vec![("cleared", cleared)]),
+            Unknown(b) => return format!("unknown {:#04x}", b),
+        };
+
+        flags.retain(|(_, set)| *set);
+        std::iter::once(kind)
+            .chain(flags.into_iter().map(|(name, _)| name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Like `describe`, but with the index of rooms, eg. "room 5, cleared".
+    pub fn name(self) -> String {
+        match self {
+            DungeonBlock::Room { index, cleared } => {
+                let mut ret = format!("room {}", index);
+                if cleared {
+                    ret.push_str(", cleared");
+                }
+                ret
+            }
+            b => b.describe(),
+        }
+    }
+
+    /// Badge colors for the subtypes of the block on annotated maps.
+    pub fn badges(self) -> Vec<Color> {
+        use DungeonBlock::*;
+
+        let mut ret = Vec::new();
+        match self {
+            Chest { trap, poisoned, .. } => {
+                if trap != 0 {
+                    ret.push(Red);
+                }
+                if poisoned {
+                    ret.push(Lime);
+                }
+            }
+            Fountain(effect) => ret.push(match effect {
+                FountainEffect::CurePoison => Aqua,
+                FountainEffect::Heal => Blue,
+                FountainEffect::Poison => Lime,
+                FountainEffect::Damage(_) => Red,
+            }),
+            Trap { kind, .. } => ret.push(match kind {
+                TrapKind::Visible => White,
+                TrapKind::Bomb => Red,
+                TrapKind::Invisible => Fuchsia,
+                TrapKind::Other(_) => Gray,
+            }),
+            _ => {}
+        }
+
+        if matches!(
+            self,
+            UpLadder {
+                upper_trap: true,
+                ..
+            } | DownLadder {
+                upper_trap: true,
+                ..
+            } | UpDownLadder {
+                upper_trap: true,
+                ..
+            } | Chest {
+                upper_trap: true,
+                ..
+            } | Trap { upper: true, .. }
+        ) {
+            ret.push(Yellow);
+        }
+
+        ret
+    }
+}
+
+impl fmt::Display for DungeonBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use DungeonBlock::*;
+
+        let c = match self {
+            Corridor => '.',
+            UpLadder { .. } => '<',
+            DownLadder { .. } => '>',
+            UpDownLadder { .. } => '↔',
+            Chest {
+                trap: 0,
+                poisoned: false,
+                ..
+            } => '$',
+            Chest { .. } => '&',
+            Fountain(_) => '{',
+            Trap {
+                kind: TrapKind::Bomb,
+                ..
+            } => '!',
+            Trap {
+                kind: TrapKind::Invisible,
+                ..
+            } => ';',
+            Trap { upper: true, .. } => 'v',
+            Trap { .. } => '^',
+            OpenChest => '$',
+            Field(_) => '*',
+            Wall { text: true, .. } => '"',
+            Wall { .. } => '#',
+            SecretDoor { .. } => '+',
+            Door { .. } => '|',
+            Room { cleared: true, .. } => '_',
+            Room { index, .. } => char::from_digit(*index as u32, 16).unwrap(),
+            Unknown(_) => '?',
+        };
+        write!(f, "{}", c)
+    }
+}
+
+impl From<u8> for DungeonBlock {
+    fn from(b: u8) -> Self {
+        use DungeonBlock::*;
+
+        let low = b & 0xf;
+        // Upper trap bit of ladders, chests and traps.
+        let upper_trap = low & 0x8 != 0;
+        // Unused bits of ladders.
+        let extra = low & 0x7;
+
+        match (b >> 4, low) {
+            (0, 0) => Corridor,
+            (1, _) => UpLadder { upper_trap, extra },
+            (2, _) => DownLadder { upper_trap, extra },
+            (3, _) => UpDownLadder { upper_trap, extra },
+            (4, _) => Chest {
+                trap: low & 0x3,
+                poisoned: low & 0x4 != 0,
+                upper_trap,
+            },
+            (5, 0) => Fountain(FountainEffect::CurePoison),
+            (5, 1) => Fountain(FountainEffect::Heal),
+            (5, 2) => Fountain(FountainEffect::Poison),
+            (5, _) => Fountain(FountainEffect::Damage(low)),
+            (6, _) => Trap {
+                kind: match low & 0x7 {
+                    0 => TrapKind::Visible,
+                    1 => TrapKind::Bomb,
+                    2 => TrapKind::Invisible,
+                    n => TrapKind::Other(n),
+                },
+                upper: upper_trap,
+            },
+            (7, 0) => OpenChest,
+            (8, _) => Field(low.into()),
+            (10, _) => Room {
+                index: low,
+                cleared: true,
+            },
+            // Only a low nibble of exactly 1 marks a text wall.
+            (11 | 12, _) => Wall {
+                secondary: b >> 4 == 12,
+                text: low == 1,
+                extra: if low == 1 { 0 } else { low },
+            },
+            (13, _) => SecretDoor { extra: low },
+            (14, _) => Door { extra: low },
+            (15, _) => Room {
+                index: low,
+                cleared: false,
+            },
+            _ => Unknown(b),
+        }
+    }
+}
+
+impl From<DungeonBlock> for u8 {
+    fn from(block: DungeonBlock) -> u8 {
+        use DungeonBlock::*;
+
+        let upper = |t: bool| if t { 0x8 } else { 0 };
+
+        match block {
+            Corridor => 0x00,
+            UpLadder { upper_trap, extra } => {
+                0x10 | upper(upper_trap) | (extra & 0x7)
+            }
+            DownLadder { upper_trap, extra } => {
+                0x20 | upper(upper_trap) | (extra & 0x7)
+            }
+            UpDownLadder { upper_trap, extra } => {
+                0x30 | upper(upper_trap) | (extra & 0x7)
+            }
+            Chest {
+                trap,
+                poisoned,
+                upper_trap,
+            } => {
+                0x40 | (trap & 0x3)
+                    | if poisoned { 0x4 } else { 0 }
+                    | upper(upper_trap)
+            }
+            Fountain(effect) => {
+                0x50 | match effect {
+                    FountainEffect::CurePoison => 0,
+                    FountainEffect::Heal => 1,
+                    FountainEffect::Poison => 2,
+                    FountainEffect::Damage(n) => n & 0xf,
+                }
+            }
+            Trap { kind, upper: u } => {
+                0x60 | match kind {
+                    TrapKind::Visible => 0,
+                    TrapKind::Bomb => 1,
+                    TrapKind::Invisible => 2,
+                    TrapKind::Other(n) => n & 0x7,
+                } | upper(u)
+            }
+            OpenChest => 0x70,
+            Field(kind) => 0x80 | (u8::from(kind) & 0xf),
+            Room { index, cleared } => {
+                (if cleared { 0xa0 } else { 0xf0 }) | (index & 0xf)
+            }
+            Wall {
+                secondary,
+                text,
+                extra,
+            } => {
+                (if secondary { 0xc0 } else { 0xb0 })
+                    | if text { 1 } else { extra & 0xf }
+            }
+            SecretDoor { extra } => 0xd0 | (extra & 0xf),
+            Door { extra } => 0xe0 | (extra & 0xf),
+            Unknown(b) => b,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn odd_low_bits() {
+        // Typed by the high nibble, with the low bits kept.
+        for b in [0xb2, 0xcf, 0x12, 0x3d, 0xd4, 0xe1] {
+            let block = DungeonBlock::from(b);
+            assert!(!matches!(block, DungeonBlock::Unknown(_)), "{:#04x}", b);
+            assert_eq!(u8::from(block), b);
+        }
+        assert!(!DungeonBlock::from(0xb2).is_open());
+        assert!(DungeonBlock::from(0x12).ladder_up());
+        assert!(DungeonBlock::from(0x3d).ladder_down());
+        assert!(matches!(
+            DungeonBlock::from(0xb1),
+            DungeonBlock::Wall { text: true, .. }
+        ));
+        assert!(matches!(
+            DungeonBlock::from(0xb3),
+            DungeonBlock::Wall { text: false, .. }
+        ));
+
+        // Every byte survives the round trip.
+        for b in 0..=255 {
+            assert_eq!(u8::from(DungeonBlock::from(b)), b);
+        }
+    }
+
+    #[test]
+    fn room_names() {
+        assert_eq!(DungeonBlock::from(0xa5).name(), "room 5, cleared");
+        assert_eq!(DungeonBlock::from(0xf5).name(), "room 5");
+        assert_eq!(DungeonBlock::from(0x51).name(), "fountain, heal");
+    }
+}
@@ -0,0 +1,73 @@
+//! 8x8 bitmap fonts from the IBM.CH and RUNES.CH files.
+
+use std::fs;
+
+use image::{ImageBuffer, Rgb};
+use lazy_static::lazy_static;
+
+use crate::U5_PATH;
+
+lazy_static! {
+    /// Latin letters and symbols.
+    pub static ref IBM: Font = Font::load("IBM.CH");
+    /// Britannian runes and remaining symbols.
+    pub static ref RUNES: Font = Font::load("RUNES.CH");
+}
+
+pub struct Font([[u8; 8]; 128]);
+
+impl Font {
+    pub const WIDTH: u32 = 8;
+    pub const HEIGHT: u32 = 8;
+
+    fn load(file: &str) -> Font {
+        let data = fs::read(U5_PATH.join(file)).unwrap();
+        let mut glyphs = [[0; 8]; 128];
+        for (glyph, bytes) in glyphs.iter_mut().zip(data.chunks(8)) {
+            glyph.copy_from_slice(bytes);
+        }
+        Font(glyphs)
+    }
+
+    /// Draw a character, pixels outside the image are clipped.
+    ///
+    /// Glyph background is left untouched if `bg` is `None`.
+    pub fn draw_char(
+        &self,
+        img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+        x: u32,
+        y: u32,
+        c: u8,
+        fg: Rgb<u8>,
+        bg: Option<Rgb<u8>>,
+    ) {
+        let glyph = &self.0[(c & 0x7f) as usize];
+        for (v, bits) in glyph.iter().enumerate() {
+            for u in 0..8 {
+                let (x, y) = (x + u, y + v as u32);
+                if x >= img.width() || y >= img.height() {
+                    continue;
+                }
+                if bits & (0x80 >> u) != 0 {
+                    img.put_pixel(x, y, fg);
+                } else if let Some(bg) = bg {
+                    img.put_pixel(x, y, bg);
+                }
+            }
+        }
+    }
+
+    pub fn draw_str(
+        &self,
+        img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+        x: u32,
+        y: u32,
+        s: &str,
+        fg: Rgb<u8>,
+        bg: Option<Rgb<u8>>,
+    ) {
+        for (i, c) in s.bytes().enumerate() {
+            self.draw_char(img, x + i as u32 * Font::WIDTH, y, c, fg, bg);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Graphs of how the floor regions of a dungeon connect between levels.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::{route::Pos, Dungeon, DungeonBlock, TrapKind};
+
+/// Connected area of open blocks on one level.
+struct Region {
+    level: usize,
+    blocks: Vec<(usize, usize)>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Link {
+    Ladder,
+    /// Assumed to drop the party to the level below.
+    Trapdoor,
+    /// Up ladder on the first level leading out of the dungeon.
+    Exit,
+}
+
+struct Edge {
+    from: usize,
+    /// `None` for leaving the dungeon.
+    to: Option<usize>,
+    link: Link,
+    /// Block of the ladder or trapdoor on the upper level.
+    at: (usize, usize),
+}
+
+struct Graph {
+    regions: Vec<Region>,
+    edges: Vec<Edge>,
+}
+
+impl Graph {
+    fn new(dungeon: &Dungeon) -> Self {
+        let block = |(z, x, y): Pos| dungeon.floors[z].0[y][x];
+
+        // Split the levels into regions.
+        let mut regions = Vec::new();
+        let mut region_of: HashMap<Pos, usize> = HashMap::new();
+        for z in 0..8 {
+            for y in 0..8 {
+                for x in 0..8 {
+                    if region_of.contains_key(&(z, x, y))
+                        || !block((z, x, y)).is_open()
+                    {
+                        continue;
+                    }
+
+                    let mut blocks = Vec::new();
+                    let mut edge = VecDeque::from([(z, x, y)]);
+                    region_of.insert((z, x, y), regions.len());
+                    while let Some(pos) = edge.pop_front() {
+                        blocks.push((pos.1, pos.2));
+                        for n in dungeon.neighbors(pos, DungeonBlock::is_open) {
+                            if n.0 == z && !region_of.contains_key(&n) {
+                                region_of.insert(n, regions.len());
+                                edge.push_back(n);
+                            }
+                        }
+                    }
+                    blocks.sort_by_key(|&(x, y)| (y, x));
+                    regions.push(Region { level: z, blocks });
+                }
+            }
+        }
+
+        // Links between levels, from the upper level down.
+        let mut edges = Vec::new();
+        for (i, r) in regions.iter().enumerate() {
+            let z = r.level;
+            for &(x, y) in &r.blocks {
+                let b = block((z, x, y));
+                if z == 0 && b.ladder_up() {
+                    edges.push(Edge {
+                        from: i,
+                        to: None,
+                        link: Link::Exit,
+                        at: (x, y),
+                    });
+                }
+                if z == 7 {
+                    continue;
+                }
+                let below = region_of.get(&(z + 1, x, y)).copied();
+                if b.ladder_down() && block((z + 1, x, y)).ladder_up() {
+                    edges.push(Edge {
+                        from: i,
+                        to: below,
+                        link: Link::Ladder,
+                        at: (x, y),
+                    });
+                }
+                if is_trapdoor(b) && below.is_some() {
+                    edges.push(Edge {
+                        from: i,
+                        to: below,
+                        link: Link::Trapdoor,
+                        at: (x, y),
+                    });
+                }
+            }
+        }
+
+        Graph { regions, edges }
+    }
+
+    /// Label of a region with its rooms and notable features.
+    fn label(&self, dungeon: &Dungeon, i: usize) -> Vec<String> {
+        use DungeonBlock::*;
+
+        let r = &self.regions[i];
+        let (x, y) = r.blocks[0];
+        let mut ret = vec![format!(
+            "Level {} ({}, {}), {} blocks",
+            r.level + 1,
+            x,
+            y,
+            r.blocks.len()
+        )];
+
+        let blocks: Vec<DungeonBlock> = r
+            .blocks
+            .iter()
+            .map(|&(x, y)| dungeon.floors[r.level].0[y][x])
+            .collect();
+        let mut rooms: Vec<u8> = blocks
+            .iter()
+            .filter_map(|b| match b {
+                Room { index, .. } => Some(*index),
+                _ => None,
+            })
+            .collect();
+        rooms.sort();
+        rooms.dedup();
+        if !rooms.is_empty() {
+            ret.push(format!(
+                "rooms {}",
+                rooms
+                    .iter()
+                    .map(|n| n.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        let mut features = Vec::new();
+        for (name, count) in [
+            (
+                "chest",
+                blocks
+                    .iter()
+                    .filter(|b| matches!(b, Chest { .. } | OpenChest))
+                    .count(),
+            ),
+            (
+                "fountain",
+                blocks.iter().filter(|b| matches!(b, Fountain(_))).count(),
+            ),
+            (
+                "field",
+                blocks.iter().filter(|b| matches!(b, Field(_))).count(),
+            ),
+            (
+                "trap",
+                blocks
+                    .iter()
+                    .filter(|b| matches!(b, Trap { .. }) && !is_trapdoor(**b))
+                    .count(),
+            ),
+            (
+                "secret door",
+                blocks
+                    .iter()
+                    .filter(|b| matches!(b, SecretDoor { .. }))
+                    .count(),
+            ),
+        ] {
+            match count {
+                0 => {}
+                1 => features.push(name.to_string()),
+                n => features.push(format!("{} {}s", n, name)),
+            }
+        }
+        if !features.is_empty() {
+            ret.push(features.join(", "));
+        }
+
+        ret
+    }
+}
+
+/// Floor traps that drop the party to the level below. Bomb traps, traps
+/// of unknown kind and traps in the ceiling don't.
+pub fn is_trapdoor(block: DungeonBlock) -> bool {
+    matches!(
+        block,
+        DungeonBlock::Trap {
+            kind: TrapKind::Visible | TrapKind::Invisible,
+            upper: false,
+        }
+    )
+}
+
+fn edge_label(e: &Edge) -> String {
+    let kind = match e.link {
+        Link::Ladder => "ladder",
+        Link::Trapdoor => "trapdoor",
+        Link::Exit => "entrance",
+    };
+    format!("{} ({}, {})", kind, e.at.0, e.at.1)
+}
+
+/// Graph of the dungeon's regions in Graphviz DOT.
+pub fn dot(dungeon: &Dungeon) -> String {
+    let graph = Graph::new(dungeon);
+    let mut ret = format!("digraph \"{}\" {{\n", dungeon.name);
+    ret.push_str("    node [shape=box];\n");
+    ret.push_str("    outside [label=\"Outside\", shape=ellipse];\n");
+
+    for z in 0..8 {
+        ret.push_str(&format!("    subgraph cluster_{} {{\n", z));
+        ret.push_str(&format!("        label=\"Level {}\";\n", z + 1));
+        for (i, r) in graph.regions.iter().enumerate() {
+            if r.level == z {
+                ret.push_str(&format!(
+                    "        r{} [label=\"{}\"];\n",
+                    i,
+                    graph.label(dungeon, i).join("\\n")
+                ));
+            }
+        }
+        ret.push_str("    }\n");
+    }
+
+    for e in &graph.edges {
+        let to = e.to.map_or("outside".to_string(), |i| format!("r{}", i));
+        let (from, to) = if e.link == Link::Exit {
+            (to, format!("r{}", e.from))
+        } else {
+            (format!("r{}", e.from), to)
+        };
+        // Ladders can be climbed both ways.
+        let style = match e.link {
+            Link::Trapdoor => ", style=dashed",
+            _ => ", dir=both",
+        };
+        ret.push_str(&format!(
+            "    {} -> {} [label=\"{}\"{}];\n",
+            from,
+            to,
+            edge_label(e),
+            style
+        ));
+    }
+
+    ret.push_str("}\n");
+    ret
+}
+
+/// Graph of the dungeon's regions as a Mermaid flowchart.
+pub fn mermaid(dungeon: &Dungeon) -> String {
+    let graph = Graph::new(dungeon);
+    let mut ret = String::from("flowchart TD\n");
+    ret.push_str("    outside([Outside])\n");
+
+    for z in 0..8 {
+        ret.push_str(&format!("    subgraph level{}[Level {}]\n", z, z + 1));
+        for (i, r) in graph.regions.iter().enumerate() {
+            if r.level == z {
+                ret.push_str(&format!(
+                    "        r{}[\"{}\"]\n",
+                    i,
+                    graph.label(dungeon, i).join("<br>")
+                ));
+            }
+        }
+        ret.push_str("    end\n");
+    }
+
+    for e in &graph.edges {
+        let to = e.to.map_or("outside".to_string(), |i| format!("r{}", i));
+        let arrow = match e.link {
+            Link::Trapdoor => "-.->",
+            _ => "<-->",
+        };
+        if e.link == Link::Exit {
+            ret.push_str(&format!(
+                "    {} {}|{}| r{}\n",
+                to,
+                arrow,
+                edge_label(e),
+                e.from
+            ));
+        } else {
+            ret.push_str(&format!(
+                "    r{} {}|{}| {}\n",
+                e.from,
+                arrow,
+                edge_label(e),
+                to
+            ));
+        }
+    }
+
+    ret
+}
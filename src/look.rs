@@ -0,0 +1,48 @@
+//! Tile descriptions from LOOK2.DAT.
+
+use std::fs;
+
+use lazy_static::lazy_static;
+
+use crate::U5_PATH;
+
+lazy_static! {
+    /// The game's "Look" description for each of the 512 tiles.
+    pub static ref LOOK: Vec<String> = {
+        let data = fs::read(U5_PATH.join("LOOK2.DAT")).unwrap();
+        data[..0x400]
+            .chunks(2)
+            .map(|b| {
+                let start = u16::from_le_bytes([b[0], b[1]]) as usize;
+                let len = data[start..]
+                    .iter()
+                    .position(|&c| c == 0)
+                    .unwrap_or(data.len() - start);
+                String::from_utf8_lossy(&data[start..start + len])
+                    .into_owned()
+            })
+            .collect()
+    };
+}
+
+/// Description of a tile.
+pub fn describe(tile: usize) -> &'static str {
+    &LOOK[tile]
+}
+
+/// Description of a tile without its leading article and with the first
+/// letter capitalized, such as "Skeleton" for "a skeleton". Empty if the
+/// tile has no description.
+pub fn name(tile: usize) -> String {
+    let desc = describe(tile).trim();
+    let desc = ["a ", "an ", "the "]
+        .iter()
+        .find_map(|a| desc.strip_prefix(a))
+        .unwrap_or(desc);
+
+    let mut chars = desc.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -1,19 +1,62 @@
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     env, fmt, fs, mem,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use image::{ImageBuffer, Rgb};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 
+mod check;
+
 mod combat_map;
 use combat_map::CombatMap;
 
+mod data_ovl;
+
+mod diff;
+
+mod dungeon_block;
+use dungeon_block::{DungeonBlock, TrapKind};
+
+mod font;
+
+mod graph;
+
+mod look;
+
+mod misc_maps;
+
+mod monsters;
+
+mod overworld;
+
+mod query;
+
+mod rooms;
+
+mod route;
+
+mod saved_game;
+use saved_game::{Facing, SavedGame};
+
+mod settlement;
+use settlement::{FIRST_DUNGEON, SETTLEMENTS};
+
+mod signs;
+use signs::{Sign, SIGNS};
+
+mod stats;
+
 mod terrain;
-use terrain::{Terrain, TERRAIN};
+use terrain::Terrain;
+
+mod tile_map;
+use tile_map::TileMap;
+
+mod tiled;
 
 lazy_static! {
     static ref U5_PATH: PathBuf = {
@@ -22,7 +65,7 @@ lazy_static! {
                 "Set environment variable ULTIMA_V_PATH to point to data files",
             )
             .into();
-        if !fs::metadata(path.join("BRIT.CBT")).map_or(false, |m| m.is_file()) {
+        if !fs::metadata(path.join("BRIT.CBT")).is_ok_and(|m| m.is_file()) {
             eprintln!("Invalid Ultima V path {:?}", path);
             std::process::exit(1);
         }
@@ -73,29 +116,25 @@ pub enum Color {
 use Color::*;
 
 lazy_static! {
-    static ref TILES: [[[Rgb<u8>; 16]; 16]; 512] = {
-        fn unpack_lzw(mut bytes: &[u8]) -> Vec<u8> {
-            let mut decoder = lzw::Decoder::new(lzw::LsbReader::new(), 8);
-            let mut ret = Vec::new();
-            loop {
-                let (len, unpacked) = decoder.decode_bytes(bytes).unwrap();
-                if len == 0 {
-                    break;
-                }
-                ret.extend_from_slice(unpacked);
-                bytes = &bytes[len..];
-            }
-            ret
-        }
-
+    /// Palette indices of the 512 16x16 tiles from TILES.16, one byte per
+    /// pixel.
+    static ref TILE_DATA: Vec<u8> = {
         // First four bytes are expected output length, skip those.
         let tiles =
             unpack_lzw(&fs::read(U5_PATH.join("TILES.16")).unwrap()[4..]);
 
         // Expand two 16-color pixels in each byte.
-        let mut tiles: Vec<u8> =
+        let tiles: Vec<u8> =
             tiles.into_iter().flat_map(|b| [b >> 4, b & 0xf]).collect();
 
+        assert_eq!(tiles.len(), 16 * 16 * 512);
+        tiles
+    };
+
+    /// Tiles as used in dungeon maps.
+    static ref TILES: [[[Rgb<u8>; 16]; 16]; 512] = {
+        let mut tiles = TILE_DATA.clone();
+
         // The game does some dynamic graphics tricks with the tiles, try to
         // replicate some here.
 
@@ -106,9 +145,9 @@ lazy_static! {
             if matches!(t, 4 | 91 | 92 | 93 | 94) {
                 continue;
             }
-            for i in t*256..(t+1)*256 {
-                if tiles[i] == Green as u8 {
-                    tiles[i] = Maroon as u8;
+            for p in &mut tiles[t*256..(t+1)*256] {
+                if *p == Green as u8 {
+                    *p = Maroon as u8;
                 }
             }
         }
@@ -124,129 +163,317 @@ lazy_static! {
             }
         }
 
-        // Convert to Rgb values.
-        let tiles: Vec<Rgb<u8>> =
-            tiles.into_iter().map(|b| EGA[b as usize]).collect();
+        // Make an open chest from the chest (257) in junk tile 205 by
+        // knocking out the lid.
+        for i in 0..256 {
+            tiles[205*256 + i] = if i < 96 {
+                Black as u8
+            } else {
+                tiles[257*256 + i]
+            };
+        }
+
+        tile_set(&tiles)
+    };
 
-        assert_eq!(tiles.len(), 16 * 16 * 512);
-        let mut ret = [[[Rgb([0, 0, 0]); 16]; 16]; 512];
-        for (i, b) in tiles.into_iter().enumerate() {
-            ret[i / 256][(i / 16) % 16][i % 16] = b;
+    /// Unmodified tiles for the surface world and settlement maps.
+    static ref WORLD_TILES: [[[Rgb<u8>; 16]; 16]; 512] = tile_set(&TILE_DATA);
+}
+
+/// Draw a map of `width` x `height` tiles.
+pub fn draw_tiles(
+    tiles: &[[[Rgb<u8>; 16]; 16]; 512],
+    width: usize,
+    height: usize,
+    tile: impl Fn(usize, usize) -> usize,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    ImageBuffer::from_fn(width as u32 * 16, height as u32 * 16, |x, y| {
+        let (x, y) = (x as usize, y as usize);
+        tiles[tile(x / 16, y / 16)][y % 16][x % 16]
+    })
+}
+
+/// Draw a one pixel rectangle outline, clipped to the image.
+pub fn draw_box(
+    img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    color: Rgb<u8>,
+) {
+    let mut plot = |u: u32, v: u32| {
+        if u < img.width() && v < img.height() {
+            img.put_pixel(u, v, color);
         }
-        ret
     };
+    for i in 0..w {
+        plot(x + i, y);
+        plot(x + i, y + h - 1);
+    }
+    for i in 0..h {
+        plot(x, y + i);
+        plot(x + w - 1, y + i);
+    }
 }
 
-lazy_static! {
-    static ref DUNGEONS: HashMap<&'static str, Dungeon> = {
-        use DungeonKind::*;
-        const DUNGEON_DATA: [(&str, DungeonKind); 8] = [
-            ("Deceit", Prison),
-            ("Despise", Cave),
-            ("Destard", Cave),
-            ("Wrong", Prison),
-            ("Covetous", Prison),
-            ("Shame", Mine),
-            ("Hythloth", Mine),
-            ("Doom", Cave),
-        ];
-
-        let dungeon_combat = fs::read(U5_PATH.join("DUNGEON.CBT")).unwrap();
-        let mut rooms: Vec<CombatMap> = dungeon_combat.chunks(mem::size_of::<combat_map::CombatMapRaw>()).map(|c| bincode::deserialize(c).unwrap()).collect();
-
-        // All dungeons except 2nd have 16 rooms. Insert dummy rooms for 2nd
-        // dungeon to line up the array with the dungeons.
-        rooms.splice(16..16, (0..16).map(|_| CombatMap::default()));
-
-        let dungeons = fs::read(U5_PATH.join("DUNGEON.DAT")).unwrap();
-        let dungeons: [[DungeonFloor; 8]; 8] =
-            bincode::deserialize(&dungeons).unwrap();
-
-        let mut ret = HashMap::new();
-        for ((d, r), (name, kind)) in dungeons.into_iter().zip(rooms.chunks(16)).zip(DUNGEON_DATA) {
-            ret.insert(name, Dungeon {
-                kind,
-                floors: d.into_iter().collect(),
-                rooms: r.to_vec(),
-            });
+/// Place two images side by side.
+fn hcat(
+    left: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    right: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let mut ret = ImageBuffer::from_pixel(
+        left.width() + right.width(),
+        left.height().max(right.height()),
+        EGA[Black as usize],
+    );
+    image::imageops::replace(&mut ret, left, 0, 0);
+    image::imageops::replace(&mut ret, right, left.width(), 0);
+    ret
+}
+
+fn unpack_lzw(mut bytes: &[u8]) -> Vec<u8> {
+    let mut decoder = lzw::Decoder::new(lzw::LsbReader::new(), 8);
+    let mut ret = Vec::new();
+    loop {
+        let (len, unpacked) = decoder.decode_bytes(bytes).unwrap();
+        if len == 0 {
+            break;
         }
+        ret.extend_from_slice(unpacked);
+        bytes = &bytes[len..];
+    }
+    ret
+}
 
-        ret
-    };
+/// Compress data into the format of the game's LZW files, with the length
+/// of the uncompressed data in the first four bytes.
+fn pack_lzw(data: &[u8]) -> Vec<u8> {
+    let mut ret = (data.len() as u32).to_le_bytes().to_vec();
+    lzw::encode(data, lzw::LsbWriter::new(&mut ret), 8).unwrap();
+    ret
+}
+
+/// Palette indices of the tiles in a tile sheet drawn with
+/// `draw_tile_sheet`.
+///
+/// Pixels that aren't in the palette are returned as errors.
+fn read_tile_sheet(
+    img: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+) -> Result<Vec<u8>, Vec<String>> {
+    if img.dimensions() != (32 * 16, 16 * 16) {
+        return Err(vec![format!(
+            "Tile sheet is {}x{} pixels instead of 512x256",
+            img.width(),
+            img.height()
+        )]);
+    }
+
+    let mut ret = vec![0; 512 * 256];
+    let mut errors = Vec::new();
+    for (x, y, p) in img.enumerate_pixels() {
+        let tile = (y / 16 * 32 + x / 16) as usize;
+        match EGA.iter().position(|c| c == p) {
+            Some(c) => {
+                ret[tile * 256 + (y % 16 * 16 + x % 16) as usize] = c as u8
+            }
+            None => errors.push(format!(
+                "Pixel ({}, {}) in tile {} has color #{:02x}{:02x}{:02x} \
+                 that isn't in the EGA palette",
+                x, y, tile, p[0], p[1], p[2]
+            )),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(ret)
+    } else {
+        Err(errors)
+    }
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
-#[serde(from = "u8")]
-pub enum DungeonBlock {
-    Corridor,
-    UpLadder,
-    DownLadder,
-    UpDownLadder,
+/// Draw all tiles in a 32 tiles wide sheet.
+fn draw_tile_sheet(
+    tiles: &[[[Rgb<u8>; 16]; 16]; 512],
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    draw_tiles(tiles, 32, 16, |x, y| y * 32 + x)
+}
 
-    Chest(u8),
-    Fountain(u8),
-    Trap(u8),
+/// Convert palette index tile data into RGB tiles.
+fn tile_set(tiles: &[u8]) -> [[[Rgb<u8>; 16]; 16]; 512] {
+    let mut ret = [[[Rgb([0, 0, 0]); 16]; 16]; 512];
+    for (i, &b) in tiles.iter().enumerate() {
+        ret[i / 256][(i / 16) % 16][i % 16] = EGA[b as usize];
+    }
+    ret
+}
 
-    OpenChest,
+// Fallback for when DATA.OVL can't be used.
+const DUNGEON_DATA: [(&str, DungeonKind); 8] = [
+    ("Deceit", DungeonKind::Prison),
+    ("Despise", DungeonKind::Cave),
+    ("Destard", DungeonKind::Cave),
+    ("Wrong", DungeonKind::Prison),
+    ("Covetous", DungeonKind::Prison),
+    ("Shame", DungeonKind::Mine),
+    ("Hythloth", DungeonKind::Mine),
+    ("Doom", DungeonKind::Cave),
+];
 
-    Field(u8),
+lazy_static! {
+    static ref DUNGEONS: Vec<Dungeon> =
+        load_dungeons(&U5_PATH).unwrap_or_else(|e| panic!("{}", e));
+}
 
-    Wall,
-    SecretDoor,
-    Door,
+/// Load the dungeons from the DUNGEON.DAT and DUNGEON.CBT in a directory.
+///
+/// Names, entrances and graphics come from the game's DATA.OVL in any case.
+fn load_dungeons(path: &Path) -> Result<Vec<Dungeon>, String> {
+    let read = |name: &str| {
+        let file = path.join(name);
+        fs::read(&file).map_err(|e| format!("{}: {}", file.display(), e))
+    };
 
-    Room(u8),
+    let names = data_ovl::dungeon_names();
+    let sprites = data_ovl::dungeon_sprites();
+
+    let dungeons: [[DungeonFloor; 8]; 8] =
+        bincode::deserialize(&read("DUNGEON.DAT")?)
+            .map_err(|e| format!("DUNGEON.DAT: {}", e))?;
+
+    let names: Vec<String> = (0..8)
+        .map(|i| {
+            names
+                .as_ref()
+                .map_or(DUNGEON_DATA[i].0.to_string(), |n| n[i].clone())
+        })
+        .collect();
+
+    let cbt = read("DUNGEON.CBT")?;
+    let (rooms, tail) = split_rooms(&names, &dungeons, &cbt)?;
+
+    let mut ret = Vec::new();
+    for (i, ((d, r), name)) in
+        dungeons.into_iter().zip(rooms).zip(names).enumerate()
+    {
+        ret.push(Dungeon {
+            name,
+            kind: sprites
+                .and_then(|s| DungeonKind::from_sprite(s[i]))
+                .unwrap_or(DUNGEON_DATA[i].1),
+            entrance: data_ovl::location_pos(FIRST_DUNGEON + i as u8),
+            floors: d.into_iter().collect(),
+            rooms: r,
+            cleared_rooms: 0,
+            cbt_tail: if i == 7 { tail.to_vec() } else { Vec::new() },
+        });
+    }
 
-    Unknown,
+    Ok(ret)
 }
 
-impl fmt::Display for DungeonBlock {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        use DungeonBlock::*;
+/// Split the rooms of DUNGEON.CBT between the dungeons.
+///
+/// The rooms of the dungeons are stored one dungeon after another, each
+/// dungeon taking as many rooms as its highest room index in DUNGEON.DAT
+/// needs. Dungeons without rooms (Despise in the original data) take no
+/// space. The returned rooms are padded to 16 per dungeon with empty ones,
+/// and returned along with any records after them.
+fn split_rooms<'a>(
+    names: &[String],
+    dungeons: &[[DungeonFloor; 8]; 8],
+    cbt: &'a [u8],
+) -> Result<(Vec<Vec<CombatMap>>, &'a [u8]), String> {
+    const SIZE: usize = mem::size_of::<combat_map::CombatMapRaw>();
+
+    if !cbt.len().is_multiple_of(SIZE) {
+        return Err(format!(
+            "DUNGEON.CBT is {} bytes, not a whole number of {} byte rooms",
+            cbt.len(),
+            SIZE
+        ));
+    }
+    let needed: usize = dungeons.iter().map(|f| room_count(f)).sum();
+    if cbt.len() / SIZE < needed {
+        return Err(format!(
+            "DUNGEON.CBT has {} rooms but DUNGEON.DAT refers to {}",
+            cbt.len() / SIZE,
+            needed
+        ));
+    }
 
-        let c = match self {
-            Corridor => '.',
-            UpLadder => '<',
-            DownLadder => '>',
-            UpDownLadder => '↔',
-            Chest(_) => '$',
-            Fountain(_) => '{',
-            Trap(_) => '^',
-            OpenChest => '$',
-            Field(_) => '*',
-            Wall => '#',
-            SecretDoor => '+',
-            Door => '|',
-            Room(n) => char::from_digit(*n as u32, 16).unwrap(),
-            Unknown => '?',
-        };
-        write!(f, "{}", c)
+    let mut records = cbt.chunks(SIZE);
+    let mut ret = Vec::new();
+    for (name, floors) in names.iter().zip(dungeons) {
+        let mut rooms: Vec<CombatMap> = records
+            .by_ref()
+            .take(room_count(floors))
+            .map(bincode::deserialize)
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("DUNGEON.CBT, {}: {}", name, e))?;
+        rooms.resize_with(16, CombatMap::default);
+        ret.push(rooms);
+    }
+
+    let tail = &cbt[needed * SIZE..];
+    if !tail.is_empty() {
+        eprintln!(
+            "Warning: {} rooms in DUNGEON.CBT after the {} used ones",
+            tail.len() / SIZE,
+            needed
+        );
     }
+
+    Ok((ret, tail))
 }
 
-impl From<u8> for DungeonBlock {
-    fn from(b: u8) -> Self {
-        use DungeonBlock::*;
+/// Sorted indices of the rooms the floors refer to.
+fn room_indices(floors: &[DungeonFloor]) -> Vec<u8> {
+    let mut ret: Vec<u8> = floors
+        .iter()
+        .flat_map(|f| f.0.iter().flatten())
+        .filter_map(|b| match b {
+            DungeonBlock::Room { index, .. } => Some(*index),
+            _ => None,
+        })
+        .collect();
+    ret.sort();
+    ret.dedup();
+    ret
+}
+
+/// Number of rooms a dungeon has stored in DUNGEON.CBT, up to its highest
+/// room index.
+fn room_count(floors: &[DungeonFloor]) -> usize {
+    room_indices(floors).last().map_or(0, |&n| n as usize + 1)
+}
+
+/// Encode the floors of the dungeons into DUNGEON.DAT.
+fn dungeon_dat(dungeons: &[Dungeon]) -> Vec<u8> {
+    dungeons
+        .iter()
+        .flat_map(|d| &d.floors)
+        .flat_map(|f| bincode::serialize(f).unwrap())
+        .collect()
+}
 
-        match b >> 4 {
-            0 => Corridor,
-            1 => UpDownLadder,
-            2 => DownLadder,
-            3 => UpDownLadder,
-            4 => Chest(b & 0xf),
-            5 => Fountain(b & 0xf),
-            6 => Trap(b & 0xf),
-            7 => OpenChest,
-            8 => Field(b & 0xf),
-            10 => Room(b & 0xf),
-            11 => Wall,
-            12 => Wall,
-            13 => SecretDoor,
-            14 => Door,
-            15 => Room(b & 0xf),
-            _ => Unknown,
+/// Encode the rooms of the dungeons into DUNGEON.CBT, laid out the way
+/// `split_rooms` reads them.
+fn dungeon_cbt(dungeons: &[Dungeon]) -> Result<Vec<u8>, String> {
+    let mut ret = Vec::new();
+    for d in dungeons {
+        let count = room_count(&d.floors);
+        for (i, room) in d.rooms[..count].iter().enumerate() {
+            ret.extend(
+                room.to_bytes()
+                    .map_err(|e| format!("{} room {}: {}", d.name, i, e))?,
+            );
         }
     }
+    for d in dungeons {
+        ret.extend(&d.cbt_tail);
+    }
+    Ok(ret)
 }
 
 #[derive(Copy, Clone, Serialize, Deserialize)]
@@ -272,6 +499,26 @@ enum DungeonKind {
 }
 
 impl DungeonKind {
+    /// Kind from the dungeon's entrance tile on the overworld.
+    fn from_sprite(tile: u8) -> Option<Self> {
+        match tile as usize {
+            t if t == Terrain::Cave as usize => Some(DungeonKind::Cave),
+            t if t == Terrain::Mine as usize => Some(DungeonKind::Mine),
+            t if t == Terrain::Dungeon as usize => Some(DungeonKind::Prison),
+            _ => None,
+        }
+    }
+
+    /// Kind from the graphics type value of SAVED.GAM.
+    fn from_graphics_type(b: u8) -> Option<Self> {
+        match b {
+            1 => Some(DungeonKind::Cave),
+            2 => Some(DungeonKind::Mine),
+            3 => Some(DungeonKind::Prison),
+            _ => None,
+        }
+    }
+
     fn wall_tile(self) -> usize {
         match self {
             DungeonKind::Prison => Terrain::BrickWall as usize,
@@ -296,29 +543,349 @@ impl DungeonKind {
 
 #[derive(Clone)]
 struct Dungeon {
+    pub name: String,
     pub kind: DungeonKind,
+    /// Overworld position of the dungeon entrance.
+    pub entrance: Option<(u8, u8)>,
     pub floors: Vec<DungeonFloor>,
     pub rooms: Vec<CombatMap>,
+    /// Bit flags for rooms whose monsters have been killed.
+    pub cleared_rooms: u16,
+    /// Records of DUNGEON.CBT after the rooms of all dungeons, kept so that
+    /// the file is written back unchanged. Only the last dungeon has them.
+    pub cbt_tail: Vec<u8>,
+}
+
+/// Tile for the feature in the center of a dungeon block.
+fn feature_tile(block: DungeonBlock) -> Option<usize> {
+    use DungeonBlock::*;
+
+    match block {
+        UpLadder { .. } => Some(Terrain::Ladder as usize),
+        DownLadder { .. } => Some(Terrain::Ladder as usize + 1),
+        // NB: Using a generated tile.
+        UpDownLadder { .. } => Some(204),
+        Chest { .. } => Some(Terrain::Chest as usize),
+        // Open chests don't seem to show up at all in the initial map data,
+        // only in saved games.
+        // NB: Using a generated tile.
+        OpenChest => Some(205),
+        Fountain(_) => Some(Terrain::Fountain as usize),
+        Trap { .. } => Some(Terrain::Trapdoor as usize),
+        Door { .. } => Some(Terrain::Door as usize),
+        SecretDoor { .. } => Some(Terrain::SecretDoor as usize),
+        _ => None,
+    }
+}
+
+/// Example blocks and the subtypes their badges stand for on annotated
+/// maps.
+const LEGEND: [(DungeonBlock, &str); 12] = {
+    use dungeon_block::FountainEffect::*;
+    use DungeonBlock::*;
+
+    [
+        (
+            Chest {
+                trap: 1,
+                poisoned: false,
+                upper_trap: false,
+            },
+            "trapped",
+        ),
+        (
+            Chest {
+                trap: 0,
+                poisoned: true,
+                upper_trap: false,
+            },
+            "poisoned",
+        ),
+        (Fountain(CurePoison), "cures poison"),
+        (Fountain(Heal), "heals"),
+        (Fountain(Poison), "poisons"),
+        (Fountain(Damage(3)), "bad taste"),
+        (
+            Trap {
+                kind: TrapKind::Visible,
+                upper: false,
+            },
+            "visible",
+        ),
+        (
+            Trap {
+                kind: TrapKind::Bomb,
+                upper: false,
+            },
+            "bomb",
+        ),
+        (
+            Trap {
+                kind: TrapKind::Invisible,
+                upper: false,
+            },
+            "invisible",
+        ),
+        (
+            Trap {
+                kind: TrapKind::Other(3),
+                upper: false,
+            },
+            "unknown kind",
+        ),
+        (
+            Trap {
+                kind: TrapKind::Visible,
+                upper: true,
+            },
+            "upper",
+        ),
+        (
+            UpLadder {
+                upper_trap: true,
+                extra: 0,
+            },
+            "upper trap",
+        ),
+    ]
+};
+
+/// Draw the subtype badges of a block in the corners of a tile.
+fn draw_badges(
+    img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+    x: u32,
+    y: u32,
+    block: DungeonBlock,
+) {
+    const CORNERS: [(u32, u32); 4] = [(0, 0), (9, 0), (0, 9), (9, 9)];
+
+    for (color, (u, v)) in block.badges().into_iter().zip(CORNERS) {
+        for j in 0..7 {
+            for i in 0..7 {
+                let c = if i == 0 || j == 0 || i == 6 || j == 6 {
+                    EGA[Black as usize]
+                } else {
+                    EGA[color as usize]
+                };
+                img.put_pixel(x + u + i, y + v + j, c);
+            }
+        }
+    }
+}
+
+/// Draw a legend explaining the badges of annotated maps. The blocks are
+/// named by the game's descriptions of their tiles.
+fn draw_legend() -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    const ROW: u32 = 20;
+
+    let entries: Vec<(DungeonBlock, String)> = LEGEND
+        .into_iter()
+        .map(|(block, subtype)| {
+            let name = feature_tile(block).map(look::name).unwrap_or_default();
+            (block, format!("{}, {}", name, subtype))
+        })
+        .collect();
+    let columns = entries.iter().map(|(_, t)| t.len()).max().unwrap_or(0);
+
+    let mut img = ImageBuffer::from_pixel(
+        ROW + 4 + columns as u32 * font::Font::WIDTH,
+        entries.len() as u32 * ROW,
+        EGA[Black as usize],
+    );
+
+    for (i, (block, text)) in entries.into_iter().enumerate() {
+        let y = i as u32 * ROW + 2;
+        if let Some(tile) = feature_tile(block) {
+            for (v, row) in TILES[tile].iter().enumerate() {
+                for (u, &p) in row.iter().enumerate() {
+                    img.put_pixel(2 + u as u32, y + v as u32, p);
+                }
+            }
+        }
+        draw_badges(&mut img, 2, y, block);
+        font::IBM.draw_str(
+            &mut img,
+            ROW + 4,
+            y + 4,
+            &text,
+            EGA[White as usize],
+            None,
+        );
+    }
+
+    img
+}
+
+/// Pixel size of a dungeon block on the map.
+const SCREEN_WIDTH: i32 = 176;
+const SCREEN_HEIGHT: i32 = 176;
+
+/// Placement of the blocks of a dungeon level on a drawn map.
+struct BlockLayout {
+    /// Mapping from unfolded block space (x, y) to the space of the 8x8
+    /// floor data array (u, v).
+    blocks: HashMap<(i32, i32), (i32, i32)>,
+    /// Pixel position of the map's top left corner in unfolded space.
+    x0: i32,
+    y0: i32,
+    width: u32,
+    height: u32,
 }
 
-#[derive(Default)]
+impl BlockLayout {
+    /// Top left pixel on the map of the block at floor position (u, v).
+    ///
+    /// If unfolding places the block in several positions, one of them is
+    /// returned.
+    pub fn block_pos(&self, u: i32, v: i32) -> Option<(u32, u32)> {
+        self.blocks
+            .iter()
+            .filter(|(_, &p)| p == (u, v))
+            .map(|(&(x, y), _)| {
+                (
+                    (x * SCREEN_WIDTH - self.x0) as u32,
+                    (y * SCREEN_HEIGHT - self.y0) as u32,
+                )
+            })
+            .min()
+    }
+
+    /// Edges between open blocks that are neighbors on the floor but not
+    /// next to each other on the map.
+    pub fn seams(&self, floor: &DungeonFloor) -> Vec<[SeamEnd; 2]> {
+        // Unfolding can draw a block more than once, pick the topmost and
+        // then leftmost copy so the seams don't change between runs.
+        let drawn_at = |p: (i32, i32)| {
+            self.blocks
+                .iter()
+                .filter(|(_, &q)| q == p)
+                .map(|(&pos, _)| pos)
+                .min_by_key(|&(x, y)| (y, x))
+        };
+
+        let mut ret = Vec::new();
+        let mut positions: Vec<_> = self.blocks.iter().collect();
+        positions.sort_by_key(|(&(x, y), _)| (y, x));
+        for (&(x, y), &(u, v)) in positions {
+            if !floor.0[v as usize][u as usize].is_open() {
+                continue;
+            }
+            for (dx, dy) in [(1, 0), (0, 1)] {
+                let n = ((u + dx).rem_euclid(8), (v + dy).rem_euclid(8));
+                if !floor.0[n.1 as usize][n.0 as usize].is_open()
+                    || self.blocks.get(&(x + dx, y + dy)) == Some(&n)
+                {
+                    continue;
+                }
+                if let Some(other) = drawn_at(n) {
+                    ret.push([((x, y), (dx, dy)), (other, (-dx, -dy))]);
+                }
+            }
+        }
+        ret
+    }
+
+    /// Draw a map with the pixels of the laid out blocks given in the pixel
+    /// space of the 8x8 floor.
+    pub fn draw(
+        &self,
+        pixel: impl Fn(u32, u32) -> Rgb<u8>,
+    ) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        image::ImageBuffer::from_fn(self.width, self.height, |x, y| {
+            let (x, y) = (x as i32, y as i32);
+            // Projected block position.
+            let (bx, by) = (
+                (self.x0 + x).div_euclid(SCREEN_WIDTH),
+                (self.y0 + y).div_euclid(SCREEN_HEIGHT),
+            );
+
+            if let Some((bx, by)) = self.blocks.get(&(bx, by)) {
+                pixel(
+                    (bx * SCREEN_WIDTH + x.rem_euclid(SCREEN_WIDTH)) as u32,
+                    (by * SCREEN_HEIGHT + y.rem_euclid(SCREEN_HEIGHT)) as u32,
+                )
+            } else {
+                EGA[Black as usize]
+            }
+        })
+    }
+}
+
+/// Drawn block position in unfolded block space and the direction of its
+/// edge at one end of a wrap seam.
+type SeamEnd = ((i32, i32), (i32, i32));
+
+#[derive(Default, PartialEq)]
 struct TileData {
     pub tile: usize,
     pub monster: Option<usize>,
     pub is_trigger: bool,
     pub is_target: bool,
+    /// Only shown when showing secrets.
+    pub is_secret: bool,
 }
 
 impl Dungeon {
+    /// The dungeon in its current state in a saved game.
+    pub fn with_save(&self, save: &SavedGame) -> Dungeon {
+        let mut ret = self.clone();
+        ret.floors = save.dungeon_floors.to_vec();
+        if let Some(i) = save.dungeon_index() {
+            ret.cleared_rooms = save.cleared_rooms(i);
+        }
+        if let Some(kind) = DungeonKind::from_graphics_type(save.graphics_type)
+        {
+            ret.kind = kind;
+        }
+        ret
+    }
+
+    /// Floor blocks (z, x, y) the party can move to from a block, along the
+    /// wrapping floor and up and down ladders.
+    pub fn neighbors(
+        &self,
+        (z, x, y): (usize, usize, usize),
+        can_enter: impl Fn(DungeonBlock) -> bool,
+    ) -> Vec<(usize, usize, usize)> {
+        let mut ret: Vec<_> = [(1, 0), (7, 0), (0, 1), (0, 7)]
+            .into_iter()
+            .map(|(dx, dy)| (z, (x + dx) % 8, (y + dy) % 8))
+            .collect();
+
+        let b = self.floors[z].0[y][x];
+        if b.ladder_up() && z > 0 {
+            ret.push((z - 1, x, y));
+        }
+        if b.ladder_down() && z < 7 {
+            ret.push((z + 1, x, y));
+        }
+
+        ret.retain(|&(z, x, y)| can_enter(self.floors[z].0[y][x]));
+        ret
+    }
+
+    /// Positions of the text wall blocks of a level.
+    pub fn text_walls(&self, z: i32) -> Vec<(u8, u8)> {
+        let mut ret = Vec::new();
+        for (y, row) in self.floors[z as usize].0.iter().enumerate() {
+            for (x, block) in row.iter().enumerate() {
+                if matches!(block, DungeonBlock::Wall { text: true, .. }) {
+                    ret.push((x as u8, y as u8));
+                }
+            }
+        }
+        ret
+    }
+
     pub fn tile(&self, x: i32, y: i32, z: i32) -> TileData {
         const DARKNESS_TILE: usize = 255;
         use DungeonBlock::*;
 
-        if z < 0 || z >= 8 {
+        if !(0..8).contains(&z) {
             return Default::default();
         }
 
-        if x < 0 || x >= 11 * 8 || y < 0 || y >= 11 * 8 {
+        if !(0..11 * 8).contains(&x) || !(0..11 * 8).contains(&y) {
             return Default::default();
         }
 
@@ -330,24 +897,29 @@ impl Dungeon {
 
         let block = self.floors[z as usize].0[block_y][block_x];
 
-        if let Room(n) = block {
+        if let Room { index: n, cleared } = block {
             let room = &self.rooms[n as usize];
             let tile = room.area[y as usize][x as usize] as usize;
 
-            let monster = room.monsters.get(&[x, y]).cloned();
-            let is_trigger = room.triggers.contains_key(&[x, y]);
+            let monster = if cleared || self.cleared_rooms & (1 << n) != 0 {
+                None
+            } else {
+                room.monster_at([x, y])
+            };
+            let is_trigger = room.triggers().any(|t| t.pos == [x, y]);
             let is_target =
-                room.triggers.iter().any(|(_, fx)| fx.contains_key(&[x, y]));
+                room.triggers().any(|t| t.changes.contains(&[x, y]));
 
             return TileData {
                 tile,
                 monster,
                 is_trigger,
                 is_target,
+                ..Default::default()
             };
         }
 
-        if matches!(block, Wall) {
+        if matches!(block, Wall { .. }) {
             return TileData {
                 tile: DARKNESS_TILE,
                 ..Default::default()
@@ -364,10 +936,10 @@ impl Dungeon {
 
         // Walls in adjacent blocks.
         let (n_wall, e_wall, w_wall, s_wall) = (
-            matches!(n, Wall),
-            matches!(e, Wall),
-            matches!(w, Wall),
-            matches!(s, Wall),
+            matches!(n, Wall { .. }),
+            matches!(e, Wall { .. }),
+            matches!(w, Wall { .. }),
+            matches!(s, Wall { .. }),
         );
 
         // Distances from edges.
@@ -416,10 +988,10 @@ impl Dungeon {
         // FIXME: This fails to align with rooms in Destard.
         // A fancier version could examine room map and align to open terrain
         // in it.
-        if (matches!(n, Room(_)) && dn == 0 && de != 5)
-            || (matches!(e, Room(_)) && de == 0 && dn != 5)
-            || (matches!(w, Room(_)) && dw == 0 && dn != 5)
-            || (matches!(s, Room(_)) && ds == 0 && de != 5)
+        if (matches!(n, Room { .. }) && dn == 0 && de != 5)
+            || (matches!(e, Room { .. }) && de == 0 && dn != 5)
+            || (matches!(w, Room { .. }) && dw == 0 && dn != 5)
+            || (matches!(s, Room { .. }) && ds == 0 && de != 5)
         {
             return TileData {
                 tile: self.kind.wall_tile(),
@@ -428,10 +1000,10 @@ impl Dungeon {
         }
         // Do dungeon-appropriate doors into the rooms.
         // XXX: Repetitious code
-        if (matches!(n, Room(_)) && dn == 0 && de == 5)
-            || (matches!(e, Room(_)) && de == 0 && dn == 5)
-            || (matches!(w, Room(_)) && dw == 0 && dn == 5)
-            || (matches!(s, Room(_)) && ds == 0 && de == 5)
+        if (matches!(n, Room { .. }) && dn == 0 && de == 5)
+            || (matches!(e, Room { .. }) && de == 0 && dn == 5)
+            || (matches!(w, Room { .. }) && dw == 0 && dn == 5)
+            || (matches!(s, Room { .. }) && ds == 0 && de == 5)
         {
             return TileData {
                 tile: self.kind.doorway_tile(),
@@ -457,38 +1029,37 @@ impl Dungeon {
         let mut tile = self.kind.floor_tile();
 
         // Draw a wall around the door.
-        if matches!(block, Door | SecretDoor) && is_center_wall {
+        if matches!(block, Door { .. } | SecretDoor { .. }) && is_center_wall {
             tile = self.kind.wall_tile();
         }
 
+        // Invisible traps are only shown with secrets.
+        let mut is_secret = false;
+
         // Center feature.
         if x == 5 && y == 5 {
-            match block {
-                UpLadder => tile = Terrain::Ladder as usize,
-                DownLadder => tile = Terrain::Ladder as usize + 1,
-                // NB: Using a generated tile.
-                UpDownLadder => tile = 204,
-                Chest(_) => tile = Terrain::Chest as usize,
-                // Open chests don't seem to show up at all in the initial map data.
-                OpenChest => tile = Terrain::Chest as usize,
-                Fountain(_) => tile = Terrain::Fountain as usize,
-                Trap(_) => tile = Terrain::Trapdoor as usize,
-                Door => tile = Terrain::Door as usize,
-                SecretDoor => tile = Terrain::SecretDoor as usize,
-                _ => {}
+            if let Some(t) = feature_tile(block) {
+                tile = t;
             }
+            is_secret = matches!(
+                block,
+                Trap {
+                    kind: TrapKind::Invisible,
+                    ..
+                }
+            );
         }
 
         // Force field.
         if let Field(force) = block {
-            let force = force as usize & 0x3;
             if is_center_wall {
-                tile = 488 + force;
+                tile = 488 + force.tile_offset();
             }
         }
 
         TileData {
             tile,
+            is_secret,
             ..Default::default()
         }
     }
@@ -503,7 +1074,9 @@ impl Dungeon {
             return EGA[0];
         }
 
-        let tile_idx = if config.show_monsters {
+        let tile_idx = if data.is_secret && !config.show_secrets {
+            self.kind.floor_tile()
+        } else if config.show_monsters {
             data.monster.unwrap_or(data.tile)
         } else {
             data.tile
@@ -511,6 +1084,15 @@ impl Dungeon {
 
         let mut pixel = TILES[tile_idx][y as usize][x as usize];
 
+        // Frame random monster slots so they stand out from the map's
+        // actual whirlpools.
+        if config.show_monsters
+            && data.monster.is_some_and(monsters::is_random)
+            && (x == 0 || y == 0 || x == 15 || y == 15)
+        {
+            return EGA[Fuchsia as usize];
+        }
+
         // Highlight trap tiles.
         if pixel == EGA[Black as usize] && config.show_secrets {
             if data.is_trigger && data.is_target {
@@ -525,14 +1107,8 @@ impl Dungeon {
         pixel
     }
 
-    pub fn draw_level_map(
-        &self,
-        config: &Config,
-        level: i32,
-    ) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
-        const SCREEN_WIDTH: i32 = 176;
-        const SCREEN_HEIGHT: i32 = 176;
-
+    /// Work out where the blocks of a level go on the drawn map.
+    pub fn level_layout(&self, config: &Config, level: i32) -> BlockLayout {
         assert!((0..8).contains(&level));
 
         // Mapping from unfolded block space (x, y) to the space of the 8x8
@@ -551,9 +1127,9 @@ impl Dungeon {
             let mut closed = HashSet::new();
 
             // Find an open block as the starting point.
-            'find_start: for y in 0..8 {
-                for x in 0..8 {
-                    if !matches!(floor[y][x], Wall) {
+            'find_start: for (y, row) in floor.iter().enumerate() {
+                for (x, block) in row.iter().enumerate() {
+                    if !matches!(block, Wall { .. }) {
                         let x = x as i32;
                         let y = y as i32;
                         open_ground.push_back(((x, y), (x, y)));
@@ -577,7 +1153,8 @@ impl Dungeon {
                         if closed.contains(&(u, v)) {
                             continue;
                         }
-                        if matches!(floor[v as usize][u as usize], Wall) {
+                        if matches!(floor[v as usize][u as usize], Wall { .. })
+                        {
                             if !open_wall.contains(&((u, v), (x, y))) {
                                 open_wall.push_back(((u, v), (x, y)));
                             }
@@ -600,7 +1177,8 @@ impl Dungeon {
                         if closed.contains(&(u, v)) {
                             continue;
                         }
-                        if matches!(floor[v as usize][u as usize], Wall) {
+                        if matches!(floor[v as usize][u as usize], Wall { .. })
+                        {
                             if !open_wall.contains(&((u, v), (x, y))) {
                                 open_wall.push_back(((u, v), (x, y)));
                             }
@@ -639,50 +1217,348 @@ impl Dungeon {
             y1 = y1.max(y + SCREEN_HEIGHT);
         }
 
-        image::ImageBuffer::from_fn(
-            (x1 - x0) as u32,
-            (y1 - y0) as u32,
-            |x, y| {
-                let (x, y) = (x as i32, y as i32);
-                // Projected block position.
-                let (bx, by) = (
-                    (x0 + x).div_euclid(SCREEN_WIDTH),
-                    (y0 + y).div_euclid(SCREEN_HEIGHT),
-                );
+        BlockLayout {
+            blocks: unfolded_blocks,
+            x0,
+            y0,
+            width: (x1 - x0) as u32,
+            height: (y1 - y0) as u32,
+        }
+    }
 
-                if let Some((bx, by)) = unfolded_blocks.get(&(bx, by)) {
-                    self.pixel(
-                        config,
-                        (bx * SCREEN_WIDTH + x.rem_euclid(SCREEN_WIDTH)) as u32,
-                        (by * SCREEN_HEIGHT + y.rem_euclid(SCREEN_HEIGHT))
-                            as u32,
-                        level,
+    pub fn draw_level_map(
+        &self,
+        config: &Config,
+        level: i32,
+    ) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        let layout = self.level_layout(config, level);
+        let BlockLayout {
+            blocks: unfolded_blocks,
+            x0,
+            y0,
+            ..
+        } = &layout;
+        let (x0, y0) = (*x0, *y0);
+
+        let mut img = layout.draw(|x, y| self.pixel(config, x, y, level));
+
+        if config.annotate {
+            for (&(x, y), &(u, v)) in unfolded_blocks {
+                let block =
+                    self.floors[level as usize].0[v as usize][u as usize];
+                // Don't give away hidden traps.
+                if !config.show_secrets
+                    && matches!(
+                        block,
+                        DungeonBlock::Trap {
+                            kind: TrapKind::Invisible,
+                            ..
+                        }
                     )
-                } else {
-                    EGA[Black as usize]
+                {
+                    continue;
                 }
-            },
-        )
+                // Badges go on the center tile.
+                let (x, y) = (
+                    (x * SCREEN_WIDTH - x0 + 5 * 16) as u32,
+                    (y * SCREEN_HEIGHT - y0 + 5 * 16) as u32,
+                );
+                draw_badges(&mut img, x, y, block);
+            }
+        }
+
+        if config.seams {
+            let floor = &self.floors[level as usize];
+            for (i, seam) in layout.seams(floor).iter().enumerate() {
+                for &((x, y), dir) in seam {
+                    let (x, y) =
+                        (x * SCREEN_WIDTH - x0, y * SCREEN_HEIGHT - y0);
+                    draw_seam_mark(&mut img, (x as u32, y as u32), dir, i);
+                }
+            }
+        }
+
+        if config.grid {
+            for (&(x, y), &(u, v)) in unfolded_blocks {
+                let (x, y) = (
+                    (x * SCREEN_WIDTH - x0) as u32,
+                    (y * SCREEN_HEIGHT - y0) as u32,
+                );
+                draw_box(
+                    &mut img,
+                    x,
+                    y,
+                    SCREEN_WIDTH as u32,
+                    SCREEN_HEIGHT as u32,
+                    EGA[Gray as usize],
+                );
+                // Label with the block's position in the floor data, which
+                // differs from where it's drawn when unfolding.
+                font::IBM.draw_str(
+                    &mut img,
+                    x + 2,
+                    y + 2,
+                    &format!("{},{}", u, v),
+                    EGA[White as usize],
+                    Some(EGA[Black as usize]),
+                );
+            }
+        }
+
+        img
     }
+
+    /// Map of a level with the tiles that differ in another version of the
+    /// dungeon drawn over a dimmed map of this one.
+    ///
+    /// Returns `None` if the level looks the same in both.
+    pub fn draw_level_diff(
+        &self,
+        other: &Dungeon,
+        config: &Config,
+        level: i32,
+    ) -> Option<ImageBuffer<Rgb<u8>, Vec<u8>>> {
+        let changed =
+            |x: i32, y: i32| self.tile(x, y, level) != other.tile(x, y, level);
+        if !(0..88).any(|y| (0..88).any(|x| changed(x, y))) {
+            return None;
+        }
+
+        let layout = self.level_layout(config, level);
+        Some(layout.draw(|x, y| {
+            if !changed((x / 16) as i32, (y / 16) as i32) {
+                let p = self.pixel(config, x, y, level);
+                return Rgb(p.0.map(|c| c / 3));
+            }
+            let (x1, y1) = (x % 16, y % 16);
+            if x1 == 0 || y1 == 0 || x1 == 15 || y1 == 15 {
+                EGA[Red as usize]
+            } else {
+                other.pixel(config, x, y, level)
+            }
+        }))
+    }
+}
+
+/// Mark one end of a wrap seam with a colored bar on the edge of the block
+/// and a letter. Both ends of a seam get the same mark.
+fn draw_seam_mark(
+    img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+    (x, y): (u32, u32),
+    (dx, dy): (i32, i32),
+    seam: usize,
+) {
+    const COLORS: [Color; 6] = [Red, Lime, Yellow, Aqua, Fuchsia, White];
+    const SYMBOLS: &[u8] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+    let color = EGA[COLORS[seam % COLORS.len()] as usize];
+
+    // Center of the edge.
+    let (cx, cy) = (
+        x as i32 + SCREEN_WIDTH / 2 * (1 + dx),
+        y as i32 + SCREEN_HEIGHT / 2 * (1 + dy),
+    );
+    let (w, h) = if dx != 0 { (4, 48) } else { (48, 4) };
+    let (bx, by) = (cx - w / 2 - dx * 2, cy - h / 2 - dy * 2);
+    for v in by..by + h {
+        for u in bx..bx + w {
+            if (0..img.width() as i32).contains(&u)
+                && (0..img.height() as i32).contains(&v)
+            {
+                img.put_pixel(u as u32, v as u32, color);
+            }
+        }
+    }
+
+    font::IBM.draw_char(
+        img,
+        (cx - dx * 12 - 4) as u32,
+        (cy - dy * 12 - 4) as u32,
+        SYMBOLS[seam % SYMBOLS.len()],
+        color,
+        Some(EGA[Black as usize]),
+    );
 }
 
 #[derive(Parser, Debug)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// Hide monsters in combat rooms.
-    #[arg(long)]
+    #[arg(long, global = true)]
     hide_monsters: bool,
-    /// Highlight trigger and target tiles in combat rooms.
-    #[arg(long)]
+    /// Highlight trigger and target tiles in combat rooms and show invisible
+    /// traps.
+    #[arg(long, global = true)]
     show_secrets: bool,
     /// Show the original 8x8 footprint instead of unfolding the dungeon.
-    #[arg(long)]
+    #[arg(long, global = true)]
     original_grid: bool,
+    /// Draw block boundaries on dungeon maps and label the blocks with
+    /// their coordinates.
+    #[arg(long, global = true)]
+    grid: bool,
+    /// Mark the edges of unfolded dungeon maps that wrap around to another
+    /// part of the map, with the same mark on both ends.
+    #[arg(long, global = true)]
+    seams: bool,
+    /// Mark chest, fountain and trap subtypes with badges and write a
+    /// legend for them and HTML pages listing room monsters on hover.
+    #[arg(long, global = true)]
+    annotate: bool,
+    /// Mark signs on surface and settlement maps and list their texts. Mark
+    /// text walls on dungeon maps, whose messages aren't decoded.
+    #[arg(long, global = true)]
+    signs: bool,
+    /// Show runic sign texts in Latin letters.
+    #[arg(long, global = true)]
+    transliterate: bool,
+    /// Draw the party's current dungeon level in its state in a SAVED.GAM
+    /// file.
+    #[arg(long, global = true, value_name = "SAVED.GAM")]
+    save: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Draw dungeon level maps (default).
+    Dungeons,
+    /// Draw the Britannia and Underworld maps.
+    Overworld,
+    /// Draw the levels of towns, dwellings, castles and keeps.
+    Settlements,
+    /// Draw the cutscene and intro maps.
+    Misc,
+    /// List sign locations and texts transliterated to Latin letters.
+    Signs {
+        /// Write the list as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check the dungeon data for broken ladders, rooms and floors.
+    Check,
+    /// Find the shortest route through a dungeon, print directions for it
+    /// and draw it on the level maps.
+    Route {
+        /// Dungeon name.
+        dungeon: String,
+        /// Destination as LEVEL,X,Y or room:N for the nearest block of
+        /// combat room N.
+        #[arg(value_parser = route::parse_target)]
+        to: route::Target,
+        /// Start as LEVEL,X,Y instead of the dungeon entrance.
+        #[arg(long, value_parser = route::parse_pos)]
+        from: Option<route::Pos>,
+        /// Allow moving through secret doors.
+        #[arg(long)]
+        secret_doors: bool,
+        /// Allow moving through force fields.
+        #[arg(long)]
+        fields: bool,
+    },
+    /// Write graphs of how the floor regions of dungeons connect through
+    /// ladders and trapdoors as Graphviz DOT.
+    Graph {
+        /// Only write the graph of one dungeon.
+        dungeon: Option<String>,
+        /// Write Mermaid flowcharts instead.
+        #[arg(long)]
+        mermaid: bool,
+    },
+    /// Count block kinds, combat rooms, monsters and triggers on each
+    /// dungeon level.
+    Stats {
+        /// Only count one dungeon.
+        dungeon: Option<String>,
+        /// Write the counts as CSV.
+        #[arg(long, conflicts_with = "json")]
+        csv: bool,
+        /// Write the counts as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Search the dungeons for features, such as `fountain:heal`,
+    /// `trap:invisible level>=5`, `room-monster:dragon` or
+    /// `chest:poisoned dungeon:doom`.
+    Find {
+        /// Query terms that all have to match.
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Draw a preview of each found block.
+        #[arg(long)]
+        preview: bool,
+    },
+    /// List where each combat room is used and draw contact sheets of the
+    /// rooms.
+    Rooms {
+        /// Only list one dungeon.
+        dungeon: Option<String>,
+        /// Write the list as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Compare the dungeons of two game data directories and draw the
+    /// changed levels.
+    Diff {
+        /// Directory with the original DUNGEON.DAT and DUNGEON.CBT.
+        #[arg(long, value_name = "DIR")]
+        base: PathBuf,
+        /// Directory with the changed DUNGEON.DAT and DUNGEON.CBT.
+        #[arg(long, value_name = "DIR")]
+        other: PathBuf,
+    },
+    /// Write the dungeon data back out as DUNGEON.DAT and DUNGEON.CBT.
+    Write {
+        /// Directory to write the files into.
+        #[arg(long, value_name = "DIR")]
+        out: PathBuf,
+    },
+    /// Draw the unmodified tiles into tiles.png for editing.
+    Sheet,
+    /// Rebuild TILES.16 from an edited tile sheet.
+    PackTiles {
+        /// Tile sheet PNG in the layout written by the sheet command.
+        sheet: PathBuf,
+        /// Directory to write TILES.16 into.
+        #[arg(long, value_name = "DIR")]
+        out: PathBuf,
+    },
+    /// Export a dungeon level as a Tiled map for editing.
+    Tiled {
+        /// Dungeon name.
+        dungeon: String,
+        /// Level number, starting from 1.
+        #[arg(value_parser = clap::value_parser!(i32).range(1..=8))]
+        level: i32,
+    },
+    /// Import edited Tiled maps of dungeon levels and write the changed
+    /// DUNGEON.DAT and DUNGEON.CBT into a directory.
+    Import {
+        /// TMJ or TMX files exported with the tiled command.
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Directory to write the files into.
+        #[arg(long, value_name = "DIR")]
+        out: PathBuf,
+    },
+    /// List tile descriptions along with their terrain classification.
+    Tiles {
+        /// Write the list as JSON.
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 struct Config {
     show_monsters: bool,
     show_secrets: bool,
     unfold: bool,
+    grid: bool,
+    seams: bool,
+    annotate: bool,
+    show_signs: bool,
+    transliterate: bool,
+    save: Option<SavedGame>,
 }
 
 impl From<Args> for Config {
@@ -691,19 +1567,1092 @@ impl From<Args> for Config {
             show_monsters: !args.hide_monsters,
             show_secrets: args.show_secrets,
             unfold: !args.original_grid,
+            grid: args.grid,
+            seams: args.seams,
+            annotate: args.annotate,
+            show_signs: args.signs,
+            transliterate: args.transliterate,
+            save: args.save.map(|path| {
+                SavedGame::load(path).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                })
+            }),
         }
     }
 }
 
-fn main() {
-    let config = Config::from(Args::parse());
+/// Lower case file name part for a location name.
+fn slug(name: &str) -> String {
+    name.chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_ascii_alphanumeric() => Some(c.to_ascii_lowercase()),
+            _ => None,
+        })
+        .collect()
+}
 
-    for (name, dungeon) in &*DUNGEONS {
-        for z in 0..8 {
-            let map = dungeon.draw_level_map(&config, z);
-            let filename = format!("{}-{}.png", name.to_lowercase(), z + 1);
+/// Draw a plain tile map with the given signs on it.
+fn draw_with_signs(
+    config: &Config,
+    map: &TileMap,
+    signs: &[&Sign],
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let mut img = map.draw(&WORLD_TILES);
+    if !config.show_signs || signs.is_empty() {
+        return img;
+    }
+
+    for (i, sign) in signs.iter().enumerate() {
+        signs::mark(&mut img, sign.x as u32, sign.y as u32, i + 1);
+    }
+    let sidebar =
+        signs::draw_sidebar(signs, img.height(), config.transliterate);
+    hcat(&img, &sidebar)
+}
+
+/// File name of a dungeon level map.
+fn level_filename(dungeon: &Dungeon, z: i32) -> String {
+    format!("{}-{}.png", slug(&dungeon.name), z + 1)
+}
+
+/// Frame the center tiles of the text wall blocks of a dungeon level.
+fn mark_text_walls(
+    config: &Config,
+    dungeon: &Dungeon,
+    z: i32,
+    img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+) {
+    if !config.show_signs {
+        return;
+    }
+
+    let layout = dungeon.level_layout(config, z);
+    for (x, y) in dungeon.text_walls(z) {
+        if let Some((px, py)) = layout.block_pos(x as i32, y as i32) {
+            draw_box(
+                img,
+                px + 5 * 16,
+                py + 5 * 16,
+                16,
+                16,
+                EGA[Yellow as usize],
+            );
+        }
+    }
+}
+
+fn draw_dungeon(config: &Config, dungeon: &Dungeon) {
+    if let Some((x, y)) = dungeon.entrance {
+        eprintln!("{}, entrance at ({}, {})", dungeon.name, x, y);
+    }
+    for z in 0..8 {
+        let mut map = dungeon.draw_level_map(config, z);
+        mark_text_walls(config, dungeon, z, &mut map);
+        let filename = level_filename(dungeon, z);
+        eprintln!("{}", filename);
+        map.save(&filename).unwrap();
+
+        if config.annotate {
+            let html = filename.replace(".png", ".html");
+            eprintln!("{}", html);
+            write_image_map(&html, &filename, &room_links(config, dungeon, z));
+        }
+    }
+}
+
+/// Tooltips listing the monsters of the combat rooms on a level map.
+fn room_links(config: &Config, dungeon: &Dungeon, z: i32) -> Vec<Link> {
+    let layout = dungeon.level_layout(config, z);
+    let mut ret = Vec::new();
+    for (v, row) in dungeon.floors[z as usize].0.iter().enumerate() {
+        for (u, block) in row.iter().enumerate() {
+            let DungeonBlock::Room { index, .. } = *block else {
+                continue;
+            };
+            let Some((x, y)) = layout.block_pos(u as i32, v as i32) else {
+                continue;
+            };
+            let room = &dungeon.rooms[index as usize];
+            ret.push(Link {
+                x,
+                y,
+                w: SCREEN_WIDTH as u32,
+                h: SCREEN_HEIGHT as u32,
+                href: None,
+                title: format!(
+                    "Room {}: {}",
+                    index,
+                    monsters::summary(
+                        room.monsters().into_values().map(|m| (m, 1))
+                    )
+                ),
+            });
+        }
+    }
+    ret
+}
+
+/// Draw the part of a route that is on one level over the level's map.
+fn mark_route(
+    img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+    layout: &BlockLayout,
+    route: &[route::Pos],
+    z: usize,
+) {
+    // Center of a block on the map.
+    let center = |&(_, x, y): &route::Pos| {
+        layout.block_pos(x as i32, y as i32).map(|(x, y)| {
+            (x + SCREEN_WIDTH as u32 / 2, y + SCREEN_HEIGHT as u32 / 2)
+        })
+    };
+    let mut fill = |x: u32, y: u32, w: u32, h: u32, color: Color| {
+        for v in y..y + h {
+            for u in x..x + w {
+                img.put_pixel(u, v, EGA[color as usize]);
+            }
+        }
+    };
+
+    // Lines between consecutive blocks that are next to each other on the
+    // map, moves across the map's edges only get the block markers.
+    for w in route.windows(2).filter(|w| w[0].0 == z && w[1].0 == z) {
+        let (Some((x1, y1)), Some((x2, y2))) = (center(&w[0]), center(&w[1]))
+        else {
+            continue;
+        };
+        if x1.abs_diff(x2) + y1.abs_diff(y2) != SCREEN_WIDTH as u32 {
+            continue;
+        }
+        let (x, y) = (x1.min(x2), y1.min(y2));
+        fill(
+            x - 3,
+            y - 3,
+            x1.abs_diff(x2) + 6,
+            y1.abs_diff(y2) + 6,
+            Black,
+        );
+        fill(
+            x - 2,
+            y - 2,
+            x1.abs_diff(x2) + 4,
+            y1.abs_diff(y2) + 4,
+            Yellow,
+        );
+    }
+
+    for (i, pos) in route.iter().enumerate().filter(|(_, p)| p.0 == z) {
+        let Some((x, y)) = center(pos) else {
+            continue;
+        };
+        let color = if i == 0 {
+            Lime
+        } else if i == route.len() - 1 {
+            Red
+        } else {
+            Yellow
+        };
+        fill(x - 7, y - 7, 14, 14, Black);
+        fill(x - 6, y - 6, 12, 12, color);
+    }
+}
+
+/// Draw an arrow showing the party's facing over a dungeon block.
+fn mark_party(
+    img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+    (x, y): (u32, u32),
+    facing: Facing,
+) {
+    const SIZE: u32 = 64;
+
+    // Arrow pointing north, with a triangular head and a narrow shaft.
+    let is_arrow = |u: u32, v: u32| {
+        let (u, v) = facing.to_north(u, v, SIZE);
+        let dx = (u as i32 - SIZE as i32 / 2).abs();
+        if v < SIZE / 2 {
+            dx <= v as i32 / 2 + 1
+        } else {
+            dx <= SIZE as i32 / 8
+        }
+    };
+
+    // Center the arrow on the block.
+    let offset = (SCREEN_WIDTH as u32 - SIZE) / 2;
+    for v in 0..SIZE {
+        for u in 0..SIZE {
+            if is_arrow(u, v) {
+                img.put_pixel(
+                    x + offset + u,
+                    y + offset + v,
+                    EGA[Yellow as usize],
+                );
+            } else if [(0, 1), (1, 0), (0, -1), (-1, 0)].iter().any(
+                |(du, dv)| {
+                    let (u, v) = (u as i32 + du, v as i32 + dv);
+                    (0..SIZE as i32).contains(&u)
+                        && (0..SIZE as i32).contains(&v)
+                        && is_arrow(u as u32, v as u32)
+                },
+            ) {
+                // Outline.
+                img.put_pixel(
+                    x + offset + u,
+                    y + offset + v,
+                    EGA[Black as usize],
+                );
+            }
+        }
+    }
+}
+
+fn draw_dungeons(config: &Config) {
+    if config.annotate {
+        eprintln!("legend.png");
+        draw_legend().save("legend.png").unwrap();
+    }
+
+    if let Some(save) = &config.save {
+        let Some(i) = save.dungeon_index() else {
+            eprintln!("Saved game is not in a dungeon");
+            std::process::exit(1);
+        };
+
+        // Only draw the level the party is on.
+        let dungeon = DUNGEONS[i].with_save(save);
+        let z = save.z as i32;
+        let mut map = dungeon.draw_level_map(config, z);
+        if let Some(pos) = dungeon
+            .level_layout(config, z)
+            .block_pos(save.x as i32, save.y as i32)
+        {
+            mark_party(&mut map, pos, save.facing);
+        }
+        mark_text_walls(config, &dungeon, z, &mut map);
+
+        let filename = level_filename(&dungeon, z);
+        eprintln!("{}", filename);
+        map.save(filename).unwrap();
+        return;
+    }
+
+    for dungeon in &*DUNGEONS {
+        draw_dungeon(config, dungeon);
+    }
+}
+
+/// Clickable region of an HTML image map.
+struct Link {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    /// Areas without a link only show their title.
+    href: Option<String>,
+    title: String,
+}
+
+/// Escape text for use in HTML attributes.
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Write an HTML page showing an image with links on it.
+fn write_image_map(filename: &str, image: &str, links: &[Link]) {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<body>\n");
+    html.push_str(&format!(
+        "<img src=\"{}\" usemap=\"#links\">\n<map name=\"links\">\n",
+        html_escape(image)
+    ));
+    for link in links {
+        let href = link.href.as_ref().map_or("nohref".to_string(), |h| {
+            format!("href=\"{}\"", html_escape(h))
+        });
+        html.push_str(&format!(
+            "<area shape=\"rect\" coords=\"{},{},{},{}\" {} \
+             title=\"{}\" alt=\"{}\">\n",
+            link.x,
+            link.y,
+            link.x + link.w,
+            link.y + link.h,
+            href,
+            html_escape(&link.title),
+            html_escape(&link.title)
+        ));
+    }
+    html.push_str("</map>\n</body>\n</html>\n");
+    fs::write(filename, html).unwrap();
+}
+
+/// Highlight and label dungeon entrances on the Britannia map.
+///
+/// Entrances are drawn open or sealed if a saved game is loaded.
+fn mark_entrances(
+    config: &Config,
+    img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+) -> Vec<Link> {
+    let mut links = Vec::new();
+    for (i, dungeon) in DUNGEONS.iter().enumerate() {
+        let Some((x, y)) = dungeon.entrance else {
+            continue;
+        };
+        let (x, y) = (x as u32 * 16, y as u32 * 16);
+
+        let (color, title) = match &config.save {
+            Some(save) if save.dungeon_open[i] => {
+                (EGA[Lime as usize], format!("{} (open)", dungeon.name))
+            }
+            Some(_) => {
+                (EGA[Red as usize], format!("{} (sealed)", dungeon.name))
+            }
+            None => (EGA[Aqua as usize], dungeon.name.clone()),
+        };
+
+        draw_box(img, x.saturating_sub(1), y.saturating_sub(1), 18, 18, color);
+        draw_box(img, x.saturating_sub(2), y.saturating_sub(2), 20, 20, color);
+        font::IBM.draw_str(
+            img,
+            x + 20,
+            y + 4,
+            &title,
+            color,
+            Some(EGA[Black as usize]),
+        );
+
+        links.push(Link {
+            x,
+            y,
+            w: 16,
+            h: 16,
+            href: Some(level_filename(dungeon, 0)),
+            title,
+        });
+    }
+    links
+}
+
+fn draw_overworld(config: &Config) {
+    for (name, z, map) in [
+        ("britannia", 0, &*overworld::BRITANNIA),
+        ("underworld", -1, &*overworld::UNDERWORLD),
+    ] {
+        let signs: Vec<&Sign> = SIGNS
+            .iter()
+            .filter(|s| s.location == 0 && s.z == z)
+            .collect();
+        let filename = format!("{}.png", name);
+        eprintln!("{}", filename);
+        let mut img = draw_with_signs(config, map, &signs);
+
+        // Dungeon entrances are all on the surface.
+        if z == 0 {
+            let links = mark_entrances(config, &mut img);
+            let html = format!("{}.html", name);
+            eprintln!("{}", html);
+            write_image_map(&html, &filename, &links);
+        }
+
+        img.save(filename).unwrap();
+    }
+}
+
+fn draw_settlements(config: &Config) {
+    for settlement in &*SETTLEMENTS {
+        for (z, map) in &settlement.levels {
+            let signs: Vec<&Sign> = SIGNS
+                .iter()
+                .filter(|s| s.location == settlement.location && s.z == *z)
+                .collect();
+            let level = match z {
+                -1 => "basement".to_string(),
+                z => (z + 1).to_string(),
+            };
+            let filename = format!("{}-{}.png", slug(settlement.name), level);
             eprintln!("{}", filename);
-            map.save(filename).unwrap();
+            draw_with_signs(config, map, &signs).save(filename).unwrap();
+        }
+    }
+}
+
+fn draw_misc_maps() {
+    for (name, map) in &*misc_maps::MISC_MAPS {
+        let filename = format!("{}.png", name);
+        eprintln!("{}", filename);
+        map.draw(&WORLD_TILES).save(filename).unwrap();
+    }
+}
+
+#[derive(Serialize)]
+struct SignInfo {
+    location: String,
+    z: i32,
+    x: u8,
+    y: u8,
+    text: String,
+}
+
+/// Name of a location, or its number if it's out of range.
+fn location_name(location: u8) -> String {
+    settlement::LOCATION_NAMES
+        .get(location as usize)
+        .map_or_else(|| format!("Location {}", location), |s| s.to_string())
+}
+
+fn list_signs(json: bool) {
+    let signs: Vec<SignInfo> = SIGNS
+        .iter()
+        .map(|sign| SignInfo {
+            location: location_name(sign.location),
+            z: sign.z,
+            x: sign.x,
+            y: sign.y,
+            text: sign.plain_text(),
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&signs).unwrap());
+        return;
+    }
+
+    for sign in &signs {
+        println!("{} z:{} ({}, {})", sign.location, sign.z, sign.x, sign.y);
+        for line in sign.text.lines() {
+            println!("    {}", line);
+        }
+    }
+}
+
+fn check_dungeons() {
+    let (mut errors, mut warnings) = (0, 0);
+    for dungeon in &*DUNGEONS {
+        for finding in check::check(dungeon) {
+            match finding.severity {
+                check::Severity::Error => errors += 1,
+                check::Severity::Warning => warnings += 1,
+            }
+            println!("{} {}", dungeon.name, finding);
+        }
+    }
+
+    eprintln!("{} errors, {} warnings", errors, warnings);
+    if errors > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn draw_route(
+    config: &Config,
+    name: &str,
+    from: Option<route::Pos>,
+    to: route::Target,
+    options: route::Options,
+) {
+    let dungeon = find_dungeon(name);
+    let Some(from) = from.or_else(|| route::entrance(dungeon)) else {
+        eprintln!("{} has no entrance ladder", dungeon.name);
+        std::process::exit(1);
+    };
+    let Some(route) = route::find(dungeon, from, to, options) else {
+        eprintln!("No route found");
+        std::process::exit(1);
+    };
+
+    println!("Start at level {} ({}, {})", from.0 + 1, from.1, from.2);
+    for (i, step) in route::directions(&route).iter().enumerate() {
+        println!("{:3}. {}", i + 1, step);
+    }
+
+    let mut levels: Vec<usize> = route.iter().map(|p| p.0).collect();
+    levels.sort();
+    levels.dedup();
+    for z in levels {
+        let mut map = dungeon.draw_level_map(config, z as i32);
+        let layout = dungeon.level_layout(config, z as i32);
+        mark_route(&mut map, &layout, &route, z);
+
+        let filename = format!("{}-{}-route.png", slug(&dungeon.name), z + 1);
+        eprintln!("{}", filename);
+        map.save(filename).unwrap();
+    }
+}
+
+/// The named dungeon or all dungeons.
+fn dungeons_named(name: Option<&str>) -> Vec<&'static Dungeon> {
+    match name {
+        Some(name) => vec![find_dungeon(name)],
+        None => DUNGEONS.iter().collect(),
+    }
+}
+
+fn list_stats(name: Option<&str>, csv: bool, json: bool) {
+    let stats: Vec<stats::LevelStats> = dungeons_named(name)
+        .into_iter()
+        .flat_map(stats::stats)
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats).unwrap());
+    } else if csv {
+        stats::print_csv(&stats);
+    } else {
+        stats::print_table(&stats);
+    }
+}
+
+fn find_features(config: &Config, query: &[String], preview: bool) {
+    let query = query::Query::parse(&query.join(" ")).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let mut count = 0;
+    for dungeon in &*DUNGEONS {
+        for m in query.find(dungeon) {
+            count += 1;
+            let (z, x, y) = m.pos;
+            let block = dungeon.floors[z].0[y][x];
+            let mut line = format!(
+                "{} level {} ({}, {}): {}",
+                dungeon.name,
+                z + 1,
+                x,
+                y,
+                block.name()
+            );
+            if !m.tiles.is_empty() {
+                let tiles: Vec<String> = m
+                    .tiles
+                    .iter()
+                    .map(|p| format!("({}, {})", p[0], p[1]))
+                    .collect();
+                line.push_str(&format!(", tiles {}", tiles.join(" ")));
+            }
+            println!("{}", line);
+
+            if preview {
+                let filename = format!(
+                    "find-{}-{}-{}-{}.png",
+                    slug(&dungeon.name),
+                    z + 1,
+                    x,
+                    y
+                );
+                eprintln!("{}", filename);
+                draw_preview(config, &m).save(filename).unwrap();
+            }
+        }
+    }
+    eprintln!("{} found", count);
+}
+
+/// Block of a search result with half a block of its surroundings and the
+/// matched room tiles outlined.
+fn draw_preview(
+    config: &Config,
+    m: &query::Match,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    const SIZE: u32 = 2 * SCREEN_WIDTH as u32;
+    const MAP_SIZE: i32 = 8 * SCREEN_WIDTH;
+
+    let (z, x, y) = m.pos;
+    let (x0, y0) = (
+        x as i32 * SCREEN_WIDTH - SCREEN_WIDTH / 2,
+        y as i32 * SCREEN_HEIGHT - SCREEN_HEIGHT / 2,
+    );
+    let mut img = ImageBuffer::from_fn(SIZE, SIZE, |u, v| {
+        m.dungeon.pixel(
+            config,
+            (x0 + u as i32).rem_euclid(MAP_SIZE) as u32,
+            (y0 + v as i32).rem_euclid(MAP_SIZE) as u32,
+            z as i32,
+        )
+    });
+    for p in &m.tiles {
+        draw_box(
+            &mut img,
+            (SCREEN_WIDTH / 2) as u32 + p[0] as u32 * 16,
+            (SCREEN_HEIGHT / 2) as u32 + p[1] as u32 * 16,
+            16,
+            16,
+            EGA[Yellow as usize],
+        );
+    }
+    img
+}
+
+fn list_rooms(config: &Config, name: Option<&str>, json: bool) {
+    let mut all = Vec::new();
+    for dungeon in dungeons_named(name) {
+        // Dungeons that refer to no rooms have none stored in DUNGEON.CBT.
+        if room_indices(&dungeon.floors).is_empty() {
+            continue;
+        }
+        let usage = rooms::usage(dungeon);
+        let filename = format!("{}-rooms.png", slug(&dungeon.name));
+        eprintln!("{}", filename);
+        rooms::draw_contact_sheet(dungeon, &usage, config.show_monsters)
+            .save(filename)
+            .unwrap();
+        all.extend(usage);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&all).unwrap());
+    } else {
+        rooms::print_report(&all);
+    }
+}
+
+fn write_graphs(name: Option<&str>, mermaid: bool) {
+    for dungeon in dungeons_named(name) {
+        let (filename, graph) = if mermaid {
+            (
+                format!("{}.mmd", slug(&dungeon.name)),
+                graph::mermaid(dungeon),
+            )
+        } else {
+            (format!("{}.dot", slug(&dungeon.name)), graph::dot(dungeon))
+        };
+        eprintln!("{}", filename);
+        fs::write(filename, graph).unwrap();
+    }
+}
+
+fn diff_dungeons(config: &Config, base: &Path, other: &Path) {
+    let load = |path: &Path| {
+        load_dungeons(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+    };
+    let (base, other) = (load(base), load(other));
+
+    for (a, b) in base.iter().zip(&other) {
+        for change in diff::blocks(a, b).into_iter().chain(diff::rooms(a, b)) {
+            println!("{} {}", a.name, change);
+        }
+
+        for z in 0..8 {
+            if let Some(img) = a.draw_level_diff(b, config, z) {
+                let filename = format!("{}-{}-diff.png", slug(&a.name), z + 1);
+                eprintln!("{}", filename);
+                img.save(filename).unwrap();
+            }
+        }
+    }
+}
+
+fn write_dungeons(dir: &Path, dungeons: &[Dungeon]) {
+    fs::create_dir_all(dir).unwrap();
+
+    let path = dir.join("DUNGEON.DAT");
+    eprintln!("{}", path.display());
+    fs::write(path, dungeon_dat(dungeons)).unwrap();
+
+    let cbt = dungeon_cbt(dungeons).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let path = dir.join("DUNGEON.CBT");
+    eprintln!("{}", path.display());
+    fs::write(path, cbt).unwrap();
+}
+
+fn draw_sheet() {
+    eprintln!("tiles.png");
+    draw_tile_sheet(&WORLD_TILES).save("tiles.png").unwrap();
+}
+
+fn pack_tiles(sheet: &Path, dir: &Path) {
+    const MAX_ERRORS: usize = 20;
+
+    let img = image::open(sheet).unwrap().to_rgb8();
+    let tiles = read_tile_sheet(&img).unwrap_or_else(|errors| {
+        for e in errors.iter().take(MAX_ERRORS) {
+            eprintln!("{}", e);
+        }
+        if errors.len() > MAX_ERRORS {
+            eprintln!("...and {} more", errors.len() - MAX_ERRORS);
+        }
+        std::process::exit(1);
+    });
+
+    // Two 16-color pixels in each byte.
+    let tiles: Vec<u8> = tiles.chunks(2).map(|p| p[0] << 4 | p[1]).collect();
+
+    fs::create_dir_all(dir).unwrap();
+    let path = dir.join("TILES.16");
+    eprintln!("{}", path.display());
+    fs::write(path, pack_lzw(&tiles)).unwrap();
+}
+
+/// Dungeon with a name, exit with an error if there's none.
+fn find_dungeon(name: &str) -> &'static Dungeon {
+    DUNGEONS
+        .iter()
+        .find(|d| slug(&d.name) == slug(name))
+        .unwrap_or_else(|| {
+            eprintln!("Unknown dungeon {}", name);
+            std::process::exit(1);
+        })
+}
+
+fn export_tiled(name: &str, z: i32) {
+    let dungeon = find_dungeon(name);
+
+    draw_sheet();
+
+    let filename = format!("{}-{}.tmj", slug(&dungeon.name), z + 1);
+    eprintln!("{}", filename);
+    let map = tiled::export(dungeon, z);
+    fs::write(filename, serde_json::to_string_pretty(&map).unwrap()).unwrap();
+}
+
+fn import_tiled(files: &[PathBuf], out: &Path) {
+    let mut dungeons = DUNGEONS.clone();
+    let mut ok = true;
+    for file in files {
+        let text = match fs::read_to_string(file) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("{}: {}", file.display(), e);
+                ok = false;
+                continue;
+            }
+        };
+        let map = if file.extension().is_some_and(|e| e == "tmx") {
+            tiled::Map::from_tmx(&text)
+        } else {
+            serde_json::from_str(&text).map_err(|e| e.to_string())
+        };
+        let result = map
+            .map_err(|e| vec![e])
+            .and_then(|map| tiled::import(&map, &mut dungeons));
+        if let Err(errors) = result {
+            for e in errors {
+                eprintln!("{}: {}", file.display(), e);
+            }
+            ok = false;
+        }
+    }
+    if !ok {
+        std::process::exit(1);
+    }
+
+    write_dungeons(out, &dungeons);
+}
+
+#[derive(Serialize)]
+struct TileInfo {
+    index: usize,
+    /// Terrain classification, from the tile description when the terrain
+    /// table doesn't have one. `None` if the tile is not classified.
+    terrain: Option<String>,
+    description: &'static str,
+}
+
+fn list_tiles(json: bool) {
+    for (tile, entry, described) in terrain::mismatches() {
+        eprintln!(
+            "Warning: tile {} is {:?} in the terrain table, but its \
+             description \"{}\" names {:?}",
+            tile,
+            entry,
+            look::describe(tile),
+            described
+        );
+    }
+
+    let tiles: Vec<TileInfo> = (0..512)
+        .map(|index| TileInfo {
+            index,
+            terrain: Some(terrain::classify(index))
+                .filter(|&t| t != Terrain::Unknown)
+                .map(|t| format!("{:?}", t)),
+            description: look::describe(index),
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&tiles).unwrap());
+        return;
+    }
+
+    for tile in &tiles {
+        println!(
+            "{:3} {:16} {}",
+            tile.index,
+            tile.terrain.as_deref().unwrap_or("-"),
+            tile.description
+        );
+    }
+}
+
+fn main() {
+    let mut args = Args::parse();
+    let command = args.command.take().unwrap_or(Command::Dungeons);
+    let config = Config::from(args);
+
+    match command {
+        Command::Dungeons => draw_dungeons(&config),
+        Command::Overworld => draw_overworld(&config),
+        Command::Settlements => draw_settlements(&config),
+        Command::Misc => draw_misc_maps(),
+        Command::Signs { json } => list_signs(json),
+        Command::Check => check_dungeons(),
+        Command::Route {
+            dungeon,
+            to,
+            from,
+            secret_doors,
+            fields,
+        } => draw_route(
+            &config,
+            &dungeon,
+            from,
+            to,
+            route::Options {
+                secret_doors,
+                fields,
+            },
+        ),
+        Command::Graph { dungeon, mermaid } => {
+            write_graphs(dungeon.as_deref(), mermaid)
+        }
+        Command::Stats { dungeon, csv, json } => {
+            list_stats(dungeon.as_deref(), csv, json)
+        }
+        Command::Find { query, preview } => {
+            find_features(&config, &query, preview)
+        }
+        Command::Rooms { dungeon, json } => {
+            list_rooms(&config, dungeon.as_deref(), json)
+        }
+        Command::Diff { base, other } => diff_dungeons(&config, &base, &other),
+        Command::Write { out } => write_dungeons(&out, &DUNGEONS),
+        Command::Sheet => draw_sheet(),
+        Command::PackTiles { sheet, out } => pack_tiles(&sheet, &out),
+        Command::Tiled { dungeon, level } => export_tiled(&dungeon, level - 1),
+        Command::Import { files, out } => import_tiled(&files, &out),
+        Command::Tiles { json } => list_tiles(json),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lzw_round_trip() {
+        let data: Vec<u8> = (0..65536u32).map(|i| (i * i / 7) as u8).collect();
+        let packed = pack_lzw(&data);
+        assert_eq!(packed[..4], 65536u32.to_le_bytes());
+        assert_eq!(unpack_lzw(&packed[4..]), data);
+    }
+
+    /// LZW decoder written independently of the lzw crate with the limits
+    /// of the game's decoder: codes start at 9 bits and grow up to 12, code
+    /// 256 clears the dictionary and 257 ends the data. Returns the data
+    /// and the number of clear codes.
+    fn reference_unpack(bytes: &[u8]) -> (Vec<u8>, usize) {
+        const CLEAR: usize = 256;
+        const END: usize = 257;
+
+        let initial: Vec<Vec<u8>> = (0..=END).map(|b| vec![b as u8]).collect();
+        let mut table = initial.clone();
+        let mut width = 9;
+        let mut pos = 0;
+        let mut prev: Option<Vec<u8>> = None;
+        let (mut ret, mut clears) = (Vec::new(), 0);
+
+        while pos + width <= bytes.len() * 8 {
+            let code = (0..width).fold(0, |code, i| {
+                let bit = (bytes[(pos + i) / 8] >> ((pos + i) % 8)) & 1;
+                code | (bit as usize) << i
+            });
+            pos += width;
+
+            if code == CLEAR {
+                table = initial.clone();
+                width = 9;
+                prev = None;
+                clears += 1;
+                continue;
+            }
+            if code == END {
+                break;
+            }
+
+            let entry = match (table.get(code), &prev) {
+                (Some(e), _) => e.clone(),
+                (None, Some(p)) if code == table.len() => {
+                    [p.as_slice(), &p[..1]].concat()
+                }
+                _ => panic!("bad code {} at bit {}", code, pos - width),
+            };
+            if let Some(p) = prev {
+                assert!(table.len() < 4096, "dictionary full without a clear");
+                table.push([p.as_slice(), &entry[..1]].concat());
+                if table.len() == 1 << width && width < 12 {
+                    width += 1;
+                }
+            }
+            ret.extend_from_slice(&entry);
+            prev = Some(entry);
+        }
+
+        (ret, clears)
+    }
+
+    #[test]
+    fn lzw_matches_game_format() {
+        // Pseudorandom data compresses badly and fills the dictionary many
+        // times over, the encoder must reset it before it grows past 12 bit
+        // codes.
+        let mut x = 1u32;
+        let data: Vec<u8> = (0..512 * 128)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                x as u8
+            })
+            .collect();
+        let packed = pack_lzw(&data);
+
+        // TILES.16 starts with the unpacked length, 0x10000 for 512 tiles.
+        assert_eq!(packed[..4], [0x00, 0x00, 0x01, 0x00]);
+        let (unpacked, clears) = reference_unpack(&packed[4..]);
+        assert_eq!(unpacked, data);
+        assert!(clears > 1);
+    }
+
+    #[test]
+    fn escaped_html() {
+        assert_eq!(
+            html_escape(r#"Room 1: 2 × "Rat" & <bat>"#),
+            "Room 1: 2 × &quot;Rat&quot; &amp; &lt;bat&gt;"
+        );
+    }
+
+    #[test]
+    fn tile_sheet_round_trip() {
+        let data: Vec<u8> =
+            (0..512 * 256).map(|i| (i % 17 % 16) as u8).collect();
+        let sheet = draw_tile_sheet(&tile_set(&data));
+        assert_eq!(read_tile_sheet(&sheet).unwrap(), data);
+    }
+
+    #[test]
+    fn dungeon_dat_round_trip() {
+        let data: Vec<u8> = (0..4096).map(|i| (i * 7) as u8).collect();
+        let floors: [[DungeonFloor; 8]; 8] =
+            bincode::deserialize(&data).unwrap();
+        let dungeons: Vec<Dungeon> = floors
+            .into_iter()
+            .map(|d| Dungeon {
+                name: String::new(),
+                kind: DungeonKind::Cave,
+                entrance: None,
+                floors: d.to_vec(),
+                rooms: Vec::new(),
+                cleared_rooms: 0,
+                cbt_tail: Vec::new(),
+            })
+            .collect();
+        assert_eq!(dungeon_dat(&dungeons), data);
+    }
+
+    #[test]
+    fn room_layout() {
+        let empty = DungeonFloor([[DungeonBlock::Corridor; 8]; 8]);
+        let mut dungeons = [[empty; 8]; 8];
+        // The first dungeon stores rooms 0 to 2, the second none and the
+        // third room 0.
+        dungeons[0][4].0[1][1] = DungeonBlock::Room {
+            index: 2,
+            cleared: false,
+        };
+        dungeons[2][0].0[0][0] = DungeonBlock::Room {
+            index: 0,
+            cleared: false,
+        };
+        let names = vec![String::new(); 8];
+
+        // Four used rooms and one after them.
+        let cbt: Vec<u8> = (0..5)
+            .flat_map(|i| {
+                let mut room = CombatMap::default();
+                room.area[0][0] = i;
+                room.to_bytes().unwrap()
+            })
+            .collect();
+        let (rooms, tail) = split_rooms(&names, &dungeons, &cbt).unwrap();
+        let size = cbt.len() / 5;
+        assert_eq!(tail, &cbt[4 * size..]);
+        assert_eq!(rooms[0][2].area[0][0], 2);
+        assert_eq!(rooms[2][0].area[0][0], 3);
+        assert!(rooms.iter().all(|r| r.len() == 16));
+
+        // Written back with the record after the used ones.
+        let written: Vec<Dungeon> = dungeons
+            .iter()
+            .zip(rooms)
+            .enumerate()
+            .map(|(i, (d, r))| Dungeon {
+                name: String::new(),
+                kind: DungeonKind::Cave,
+                entrance: None,
+                floors: d.to_vec(),
+                rooms: r,
+                cleared_rooms: 0,
+                cbt_tail: if i == 7 { tail.to_vec() } else { Vec::new() },
+            })
+            .collect();
+        assert_eq!(dungeon_cbt(&written).unwrap(), cbt);
+
+        assert!(split_rooms(&names, &dungeons, &cbt[..3 * size]).is_err());
+        assert!(split_rooms(&names, &dungeons, &cbt[..cbt.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn wrap_seams() {
+        // Open corridor row along the top of the floor, unfolded so that
+        // block 7 is drawn left of block 0.
+        let mut floor = DungeonFloor(
+            [[DungeonBlock::Wall {
+                secondary: false,
+                text: false,
+                extra: 0,
+            }; 8]; 8],
+        );
+        floor.0[0] = [DungeonBlock::Corridor; 8];
+        let layout = || BlockLayout {
+            blocks: (0..8).map(|u| ((u - 1, 0), ((u + 7) % 8, 0))).collect(),
+            x0: -SCREEN_WIDTH,
+            y0: 0,
+            width: 8 * SCREEN_WIDTH as u32,
+            height: SCREEN_HEIGHT as u32,
+        };
+        assert_eq!(
+            layout().seams(&floor),
+            vec![[((6, 0), (1, 0)), ((-1, 0), (-1, 0))]]
+        );
+
+        // With block 7 drawn a second time further down, the seam goes to
+        // the topmost copy. Each new layout has its own hash map order.
+        for _ in 0..20 {
+            let mut layout = layout();
+            layout.blocks.insert((-1, 3), (7, 0));
+            assert_eq!(
+                layout.seams(&floor)[0],
+                [((6, 0), (1, 0)), ((-1, 0), (-1, 0))]
+            );
         }
     }
 }
@@ -0,0 +1,42 @@
+//! Cutscene and intro maps from MISCMAPS.DAT.
+
+use std::fs;
+
+use lazy_static::lazy_static;
+
+use crate::{tile_map::TileMap, U5_PATH};
+
+/// Intro maps in the order they appear in the introduction.
+const INTRO_NAMES: [&str; 4] = [
+    "the-summoning",
+    "the-journey",
+    "the-arrival",
+    "the-welcoming",
+];
+
+lazy_static! {
+    /// Named cutscene and intro maps.
+    pub static ref MISC_MAPS: Vec<(String, TileMap)> = {
+        let data = fs::read(U5_PATH.join("MISCMAPS.DAT")).unwrap();
+        let mut ret = Vec::new();
+
+        // Four 11x11 cutscene maps with 16 byte rows.
+        for (i, map) in data[..0x2c0].chunks(11 * 16).enumerate() {
+            ret.push((
+                format!("cutscene-{}", i + 1),
+                TileMap::from_padded_rows(11, 11, 16, map),
+            ));
+        }
+
+        // Four 19x4 intro maps with 32 byte rows.
+        let intro_maps = data[0x2c0..0x4c0].chunks(4 * 32);
+        for (name, map) in INTRO_NAMES.iter().zip(intro_maps) {
+            ret.push((
+                format!("intro-{}", name),
+                TileMap::from_padded_rows(19, 4, 32, map),
+            ));
+        }
+
+        ret
+    };
+}
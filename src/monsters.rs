@@ -0,0 +1,99 @@
+//! Names of combat room monsters.
+
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use crate::look;
+
+/// Monster tiles that are placeholders for a random monster picked by the
+/// game when the room is entered. They are drawn as a whirlpool.
+///
+/// The game data isn't known to have a table of which monsters each
+/// dungeon can pick from, so no candidates are listed for them.
+pub const RANDOM: RangeInclusive<usize> = 0x1ec..=0x1ef;
+
+pub fn is_random(tile: usize) -> bool {
+    RANDOM.contains(&tile)
+}
+
+/// Name of the monster drawn with a tile.
+///
+/// Monsters are animated with groups of four tiles, the name is the game's
+/// look description of the group's first tile. The monster name strings in
+/// DATA.OVL aren't used since it isn't known how they line up with tiles.
+pub fn name(tile: usize) -> String {
+    if is_random(tile) {
+        return "Random monster".into();
+    }
+    let name = look::name(tile & !3);
+    if name.is_empty() {
+        format!("Tile {}", tile)
+    } else {
+        name
+    }
+}
+
+/// Counts of monsters by name, most common first. Random monsters are left
+/// out.
+fn count(
+    monsters: &[(usize, usize)],
+    name: impl Fn(usize) -> String,
+) -> Vec<(String, usize)> {
+    let mut counts = BTreeMap::new();
+    for &(m, n) in monsters.iter().filter(|(m, _)| !is_random(*m)) {
+        *counts.entry(name(m)).or_insert(0) += n;
+    }
+    let mut ret: Vec<_> = counts.into_iter().collect();
+    ret.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ret
+}
+
+/// List of monsters like "3 × Skeleton, 1 × Lich, 2 random" from (tile,
+/// count) pairs.
+pub fn summary(monsters: impl IntoIterator<Item = (usize, usize)>) -> String {
+    summary_by(monsters, name)
+}
+
+fn summary_by(
+    monsters: impl IntoIterator<Item = (usize, usize)>,
+    name: impl Fn(usize) -> String,
+) -> String {
+    let monsters: Vec<(usize, usize)> = monsters.into_iter().collect();
+    let mut ret: Vec<String> = count(&monsters, name)
+        .iter()
+        .map(|(name, n)| format!("{} × {}", n, name))
+        .collect();
+    let random: usize = monsters
+        .iter()
+        .filter(|(m, _)| is_random(*m))
+        .map(|(_, n)| n)
+        .sum();
+    if random > 0 {
+        ret.push(format!("{} random", random));
+    }
+    if ret.is_empty() {
+        return "no monsters".into();
+    }
+    ret.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_monsters_listed_once() {
+        let name = |m: usize| format!("Tile {}", m & !3);
+        assert_eq!(
+            summary_by(
+                [(0x1ec, 1), (300, 1), (301, 1), (0x1ef, 1), (400, 1)],
+                name
+            ),
+            "2 × Tile 300, 1 × Tile 400, 2 random"
+        );
+        assert_eq!(
+            summary_by([(300, 3), (0x1ec, 2)], name),
+            "3 × Tile 300, 2 random"
+        );
+        assert_eq!(summary_by([], name), "no monsters");
+    }
+}
@@ -0,0 +1,47 @@
+//! Britannia and Underworld surface maps.
+
+use std::fs;
+
+use lazy_static::lazy_static;
+
+use crate::{data_ovl, tile_map::TileMap, U5_PATH};
+
+lazy_static! {
+    pub static ref BRITANNIA: TileMap = {
+        let data = fs::read(U5_PATH.join("BRIT.DAT")).unwrap();
+        // All-water chunks are left out of BRIT.DAT, the chunk table in
+        // DATA.OVL tells which chunks are stored.
+        let chunks: Vec<Option<&[u8]>> = data_ovl::brit_chunks()
+            .iter()
+            .map(|&c| (c != 0xff).then(|| chunk(&data, c as usize)))
+            .collect();
+        assemble(&chunks)
+    };
+
+    pub static ref UNDERWORLD: TileMap = {
+        let data = fs::read(U5_PATH.join("UNDER.DAT")).unwrap();
+        let chunks: Vec<Option<&[u8]>> =
+            (0..256).map(|c| Some(chunk(&data, c))).collect();
+        assemble(&chunks)
+    };
+}
+
+fn chunk(data: &[u8], idx: usize) -> &[u8] {
+    &data[idx * 256..(idx + 1) * 256]
+}
+
+/// Build the 256x256 world from 16x16 chunks stored from west to east,
+/// north to south. Missing chunks are deep water.
+fn assemble(chunks: &[Option<&[u8]>]) -> TileMap {
+    const WATER: u8 = 1;
+
+    let mut tiles = vec![WATER; 256 * 256];
+    for (i, chunk) in chunks.iter().enumerate() {
+        let Some(chunk) = chunk else { continue };
+        let (cx, cy) = ((i % 16) * 16, (i / 16) * 16);
+        for (j, &t) in chunk.iter().enumerate() {
+            tiles[(cy + j / 16) * 256 + cx + j % 16] = t;
+        }
+    }
+    TileMap::new(256, 256, tiles)
+}
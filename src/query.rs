@@ -0,0 +1,287 @@
+//! Queries for finding dungeon features.
+//!
+//! A query is a list of whitespace separated terms that all have to match:
+//!
+//! * `KIND` or `KIND:SUBTYPE` matches blocks by their description, such as
+//!   `fountain:heal`, `trap:invisible`, `chest:poisoned` or `ladder`.
+//! * `room:N` matches the blocks of combat room N.
+//! * `room-monster:NAME` matches rooms with a monster whose name contains
+//!   NAME, or with random monsters for `room-monster:random`.
+//! * `dungeon:NAME` limits the search to one dungeon.
+//! * `level:N`, `level>=N`, `level<=N`, `level>N` and `level<N` limit the
+//!   search to levels, starting from 1.
+
+use crate::{monsters, route::Pos, slug, Dungeon, DungeonBlock};
+
+#[derive(Clone, Debug)]
+enum Term {
+    Kind {
+        kind: String,
+        subtype: Option<String>,
+    },
+    Room(u8),
+    RoomMonster(String),
+    Dungeon(String),
+    /// Inclusive range of levels starting from 1.
+    Level(usize, usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct Query(Vec<Term>);
+
+/// Block that matched a query.
+pub struct Match<'a> {
+    pub dungeon: &'a Dungeon,
+    pub pos: Pos,
+    /// Room tiles that matched, such as the positions of the searched
+    /// monsters.
+    pub tiles: Vec<[u8; 2]>,
+}
+
+impl Query {
+    pub fn parse(s: &str) -> Result<Query, String> {
+        let terms = s
+            .split_whitespace()
+            .map(parse_term)
+            .collect::<Result<Vec<_>, _>>()?;
+        if terms.is_empty() {
+            return Err("Empty query".into());
+        }
+        Ok(Query(terms))
+    }
+
+    /// Find the matching blocks of a dungeon.
+    pub fn find<'a>(&self, dungeon: &'a Dungeon) -> Vec<Match<'a>> {
+        let mut ret = Vec::new();
+        for z in 0..8 {
+            for y in 0..8 {
+                for x in 0..8 {
+                    if let Some(tiles) = self.matches(dungeon, (z, x, y)) {
+                        ret.push(Match {
+                            dungeon,
+                            pos: (z, x, y),
+                            tiles,
+                        });
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    /// Room tiles of the match if the block matches.
+    fn matches(
+        &self,
+        dungeon: &Dungeon,
+        (z, x, y): Pos,
+    ) -> Option<Vec<[u8; 2]>> {
+        let block = dungeon.floors[z].0[y][x];
+        let mut tiles = Vec::new();
+        for term in &self.0 {
+            match term {
+                Term::Kind { kind, subtype } => {
+                    let desc: Vec<String> =
+                        block.describe().split(", ").map(norm).collect();
+                    // "ladder" finds all kinds of ladders.
+                    if !(desc[0] == *kind
+                        || kind == "ladder" && desc[0].ends_with("-ladder"))
+                    {
+                        return None;
+                    }
+                    if let Some(subtype) = subtype {
+                        if !desc[1..].contains(subtype) {
+                            return None;
+                        }
+                    }
+                }
+                Term::Room(n) => {
+                    if !matches!(
+                        block,
+                        DungeonBlock::Room { index, .. } if index == *n
+                    ) {
+                        return None;
+                    }
+                }
+                Term::RoomMonster(name) => {
+                    let DungeonBlock::Room { index, .. } = block else {
+                        return None;
+                    };
+                    let mut found: Vec<[u8; 2]> = dungeon.rooms[index as usize]
+                        .monsters()
+                        .into_iter()
+                        .filter(|&(_, m)| {
+                            norm(&monsters::name(m)).contains(name.as_str())
+                                || (name == "random" && monsters::is_random(m))
+                        })
+                        .map(|(p, _)| p)
+                        .collect();
+                    if found.is_empty() {
+                        return None;
+                    }
+                    found.sort_by_key(|p| (p[1], p[0]));
+                    tiles.extend(found);
+                }
+                Term::Dungeon(name) => {
+                    if slug(&dungeon.name) != *name {
+                        return None;
+                    }
+                }
+                Term::Level(min, max) => {
+                    if !(*min..=*max).contains(&(z + 1)) {
+                        return None;
+                    }
+                }
+            }
+        }
+        Some(tiles)
+    }
+}
+
+/// Lowercase words joined with dashes, as written in queries.
+fn norm(s: &str) -> String {
+    s.trim().to_lowercase().replace(' ', "-")
+}
+
+fn parse_term(s: &str) -> Result<Term, String> {
+    let level = |n: &str| {
+        n.parse::<usize>()
+            .ok()
+            .filter(|n| (1..=8).contains(n))
+            .ok_or_else(|| format!("Bad level in {}, expected 1-8", s))
+    };
+
+    if let Some(rest) = s.strip_prefix("level") {
+        return match rest.as_bytes() {
+            [b'>', b'=', ..] => Ok(Term::Level(level(&rest[2..])?, 8)),
+            [b'<', b'=', ..] => Ok(Term::Level(1, level(&rest[2..])?)),
+            [b'>', ..] => match level(&rest[1..])? {
+                8 => Err(format!("No levels match {}", s)),
+                n => Ok(Term::Level(n + 1, 8)),
+            },
+            [b'<', ..] => match level(&rest[1..])? {
+                1 => Err(format!("No levels match {}", s)),
+                n => Ok(Term::Level(1, n - 1)),
+            },
+            [b':' | b'=', ..] => {
+                let n = level(&rest[1..])?;
+                Ok(Term::Level(n, n))
+            }
+            _ => Err(format!("Bad level term {}", s)),
+        };
+    }
+
+    let (key, value) = match s.split_once(':') {
+        Some((key, value)) => (norm(key), Some(norm(value))),
+        None => (norm(s), None),
+    };
+    let value = value.filter(|v| !v.is_empty());
+    match (key.as_str(), value) {
+        ("dungeon", Some(name)) => Ok(Term::Dungeon(slug(&name))),
+        ("room-monster", Some(name)) => Ok(Term::RoomMonster(name)),
+        ("room", Some(n)) if n.chars().all(|c| c.is_ascii_digit()) => n
+            .parse()
+            .ok()
+            .filter(|&n| n < 16)
+            .map(Term::Room)
+            .ok_or_else(|| format!("Bad room {}, expected 0-15", n)),
+        ("dungeon" | "room-monster", None) => {
+            Err(format!("Missing value in {}", s))
+        }
+        ("", _) => Err(format!("Bad term {}", s)),
+        (_, subtype) => Ok(Term::Kind { kind: key, subtype }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_terms() {
+        assert!(matches!(parse_term("level>=5"), Ok(Term::Level(5, 8))));
+        assert!(matches!(parse_term("level<3"), Ok(Term::Level(1, 2))));
+        assert!(matches!(parse_term("room:12"), Ok(Term::Room(12))));
+        assert!(matches!(
+            parse_term("trap:invisible"),
+            Ok(Term::Kind { kind, subtype: Some(s) })
+                if kind == "trap" && s == "invisible"
+        ));
+        assert!(matches!(
+            parse_term("Dungeon:Doom"),
+            Ok(Term::Dungeon(d)) if d == "doom"
+        ));
+        assert!(parse_term("level>9").is_err());
+        assert!(parse_term("level>8").is_err());
+        assert!(parse_term("level<1").is_err());
+        assert!(parse_term("room-monster:").is_err());
+    }
+
+    #[test]
+    fn match_blocks() {
+        use crate::{
+            combat_map::CombatMap, dungeon_block::FountainEffect, DungeonFloor,
+            DungeonKind,
+        };
+
+        let mut floor = DungeonFloor([[DungeonBlock::Corridor; 8]; 8]);
+        floor.0[0][0] = DungeonBlock::Fountain(FountainEffect::Heal);
+        floor.0[0][1] = DungeonBlock::Chest {
+            trap: 0,
+            poisoned: true,
+            upper_trap: false,
+        };
+        floor.0[0][2] = DungeonBlock::OpenChest;
+        floor.0[0][3] = DungeonBlock::UpDownLadder {
+            upper_trap: false,
+            extra: 0,
+        };
+        floor.0[0][4] = DungeonBlock::Room {
+            index: 2,
+            cleared: false,
+        };
+        let mut floors = vec![floor; 8];
+        floors[5].0[0][0] = DungeonBlock::Fountain(FountainEffect::Poison);
+
+        let mut rooms = vec![CombatMap::default(); 16];
+        rooms[2].set_monsters([([3, 4], 0x1ec)]).unwrap();
+        let dungeon = Dungeon {
+            name: "Doom".into(),
+            kind: DungeonKind::Cave,
+            entrance: None,
+            floors,
+            rooms,
+            cleared_rooms: 0,
+            cbt_tail: Vec::new(),
+        };
+
+        // Matches on the blocks along the top row of all levels.
+        let found = |s: &str| -> Vec<(Pos, Vec<[u8; 2]>)> {
+            let query = Query::parse(s).unwrap();
+            let mut ret = Vec::new();
+            for z in 0..8 {
+                for x in 0..5 {
+                    if let Some(tiles) = query.matches(&dungeon, (z, x, 0)) {
+                        ret.push(((z, x, 0), tiles));
+                    }
+                }
+            }
+            ret
+        };
+
+        // Level 6 has a poison fountain instead.
+        assert_eq!(found("fountain:heal level>=5").len(), 3);
+        assert_eq!(found("fountain:poison").len(), 1);
+        assert_eq!(
+            found("chest:poisoned dungeon:doom level:3"),
+            vec![((2, 1, 0), vec![])]
+        );
+        // Open chests aren't chests.
+        assert_eq!(found("chest").len(), 8);
+        assert_eq!(found("ladder level:1"), vec![((0, 3, 0), vec![])]);
+        assert_eq!(
+            found("room-monster:random level<2"),
+            vec![((0, 4, 0), vec![[3, 4]])]
+        );
+        assert!(found("fountain dungeon:deceit").is_empty());
+    }
+}
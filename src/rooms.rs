@@ -0,0 +1,155 @@
+//! Cross-reference of where the combat rooms of dungeons are used.
+
+use image::{ImageBuffer, Rgb};
+use serde::Serialize;
+
+use crate::{
+    draw_tiles,
+    font::{Font, IBM},
+    monsters,
+    Color::*,
+    Dungeon, DungeonBlock, EGA, TILES,
+};
+
+#[derive(Serialize)]
+pub struct RoomUsage {
+    pub dungeon: String,
+    pub index: u8,
+    /// Blocks referring to the room as (level, x, y) with levels starting
+    /// from 1. Empty for unused rooms.
+    pub places: Vec<(usize, usize, usize)>,
+    pub monsters: String,
+}
+
+/// Where each of the 16 rooms of a dungeon is used.
+pub fn usage(dungeon: &Dungeon) -> Vec<RoomUsage> {
+    (0..16)
+        .map(|index| {
+            let mut places = Vec::new();
+            for (z, floor) in dungeon.floors.iter().enumerate() {
+                for (y, row) in floor.0.iter().enumerate() {
+                    for (x, block) in row.iter().enumerate() {
+                        if matches!(
+                            block,
+                            DungeonBlock::Room { index: i, .. } if *i == index
+                        ) {
+                            places.push((z + 1, x, y));
+                        }
+                    }
+                }
+            }
+            RoomUsage {
+                dungeon: dungeon.name.clone(),
+                index,
+                places,
+                monsters: monsters::summary(
+                    dungeon.rooms[index as usize]
+                        .monsters()
+                        .into_values()
+                        .map(|m| (m, 1)),
+                ),
+            }
+        })
+        .collect()
+}
+
+fn place_name((z, x, y): (usize, usize, usize)) -> String {
+    format!("L{} {},{}", z, x, y)
+}
+
+/// Print the rooms with the blocks they are used in.
+pub fn print_report(usage: &[RoomUsage]) {
+    for room in usage {
+        let places = if room.places.is_empty() {
+            "UNUSED".to_string()
+        } else {
+            room.places
+                .iter()
+                .map(|&(z, x, y)| format!("level {} ({}, {})", z, x, y))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        println!(
+            "{} room {:2}: {}; {}",
+            room.dungeon, room.index, places, room.monsters
+        );
+    }
+}
+
+/// Draw every room of a dungeon once at full size with the places it is
+/// used in written under it.
+pub fn draw_contact_sheet(
+    dungeon: &Dungeon,
+    usage: &[RoomUsage],
+    show_monsters: bool,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    const MARGIN: u32 = 8;
+    const COLUMNS: u32 = 4;
+    const ROOM_SIZE: u32 = 11 * 16;
+    // Places that fit on a caption line, a place takes up to 7 characters
+    // with the separating space.
+    const PER_LINE: usize = (ROOM_SIZE / Font::WIDTH) as usize / 7;
+
+    let captions: Vec<Vec<String>> = usage
+        .iter()
+        .map(|room| {
+            let mut lines = vec![format!("Room {}", room.index)];
+            if room.places.is_empty() {
+                lines.push("UNUSED".into());
+            }
+            for chunk in room.places.chunks(PER_LINE) {
+                lines.push(
+                    chunk
+                        .iter()
+                        .map(|&p| place_name(p))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                );
+            }
+            lines
+        })
+        .collect();
+    let caption_lines =
+        captions.iter().map(|c| c.len()).max().unwrap_or(0) as u32;
+
+    let cell_w = ROOM_SIZE + MARGIN;
+    let cell_h = ROOM_SIZE + caption_lines * Font::HEIGHT + 2 * MARGIN;
+    let rows = (usage.len() as u32).div_ceil(COLUMNS);
+    let mut img = ImageBuffer::from_pixel(
+        COLUMNS * cell_w + MARGIN,
+        rows * cell_h + MARGIN,
+        EGA[Black as usize],
+    );
+
+    for (i, (room, caption)) in usage.iter().zip(&captions).enumerate() {
+        let (x, y) = (
+            MARGIN + i as u32 % COLUMNS * cell_w,
+            MARGIN + i as u32 / COLUMNS * cell_h,
+        );
+        let map = &dungeon.rooms[room.index as usize];
+        let tiles = draw_tiles(&TILES, 11, 11, |u, v| {
+            map.monster_at([u as u8, v as u8])
+                .filter(|_| show_monsters)
+                .unwrap_or(map.area[v][u] as usize)
+        });
+        image::imageops::replace(&mut img, &tiles, x, y);
+
+        for (j, line) in caption.iter().enumerate() {
+            let color = if room.places.is_empty() && j > 0 {
+                EGA[Red as usize]
+            } else {
+                EGA[White as usize]
+            };
+            IBM.draw_str(
+                &mut img,
+                x,
+                y + ROOM_SIZE + MARGIN / 2 + j as u32 * Font::HEIGHT,
+                line,
+                color,
+                None,
+            );
+        }
+    }
+
+    img
+}
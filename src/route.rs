@@ -0,0 +1,195 @@
+//! Shortest routes through dungeons.
+
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+
+use crate::{Dungeon, DungeonBlock};
+
+/// Block position (level, x, y) in a dungeon.
+pub type Pos = (usize, usize, usize);
+
+/// Route destination.
+#[derive(Copy, Clone, Debug)]
+pub enum Target {
+    Block(Pos),
+    /// Nearest block of a combat room.
+    Room(u8),
+}
+
+impl Target {
+    fn is_reached(self, dungeon: &Dungeon, (z, x, y): Pos) -> bool {
+        match self {
+            Target::Block(p) => p == (z, x, y),
+            Target::Room(n) => matches!(
+                dungeon.floors[z].0[y][x],
+                DungeonBlock::Room { index, .. } if index == n
+            ),
+        }
+    }
+}
+
+/// Parse a position given as LEVEL,X,Y with levels starting from 1.
+pub fn parse_pos(s: &str) -> Result<Pos, String> {
+    let parts: Vec<usize> = s
+        .split(',')
+        .map(|p| p.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("Bad position {}, expected LEVEL,X,Y", s))?;
+    match parts[..] {
+        [z, x, y] if (1..=8).contains(&z) && x < 8 && y < 8 => {
+            Ok((z - 1, x, y))
+        }
+        _ => Err(format!(
+            "Bad position {}, expected LEVEL,X,Y with level 1-8 and x, y 0-7",
+            s
+        )),
+    }
+}
+
+/// Parse a destination given as LEVEL,X,Y or room:N.
+pub fn parse_target(s: &str) -> Result<Target, String> {
+    match s.strip_prefix("room:") {
+        Some(n) => n
+            .parse()
+            .ok()
+            .filter(|&n| n < 16)
+            .map(Target::Room)
+            .ok_or_else(|| format!("Bad room {}, expected 0-15", n)),
+        None => parse_pos(s).map(Target::Block),
+    }
+}
+
+/// Which blocks besides open floor the route may pass through.
+#[derive(Copy, Clone, Default)]
+pub struct Options {
+    pub secret_doors: bool,
+    pub fields: bool,
+}
+
+impl Options {
+    fn can_enter(self, block: DungeonBlock) -> bool {
+        match block {
+            DungeonBlock::Wall { .. } => false,
+            DungeonBlock::SecretDoor { .. } => self.secret_doors,
+            DungeonBlock::Field(_) => self.fields,
+            _ => true,
+        }
+    }
+}
+
+/// Up ladder on the first level that the party enters the dungeon by.
+pub fn entrance(dungeon: &Dungeon) -> Option<Pos> {
+    (0..8)
+        .flat_map(|y| (0..8).map(move |x| (0, x, y)))
+        .find(|&(z, x, y)| dungeon.floors[z].0[y][x].ladder_up())
+}
+
+/// Shortest route from a block to a target, including both ends.
+pub fn find(
+    dungeon: &Dungeon,
+    from: Pos,
+    to: Target,
+    options: Options,
+) -> Option<Vec<Pos>> {
+    // Where each reached block was reached from.
+    let mut came_from = HashMap::from([(from, from)]);
+    let mut edge = VecDeque::from([from]);
+
+    while let Some(pos) = edge.pop_front() {
+        if to.is_reached(dungeon, pos) {
+            let mut ret = vec![pos];
+            let mut pos = pos;
+            while pos != from {
+                pos = came_from[&pos];
+                ret.push(pos);
+            }
+            ret.reverse();
+            return Some(ret);
+        }
+
+        for n in dungeon.neighbors(pos, |b| options.can_enter(b)) {
+            if let Entry::Vacant(e) = came_from.entry(n) {
+                e.insert(pos);
+                edge.push_back(n);
+            }
+        }
+    }
+
+    None
+}
+
+/// Step by step directions for following a route.
+///
+/// Consecutive moves in the same direction are merged into one step.
+pub fn directions(route: &[Pos]) -> Vec<String> {
+    let mut ret = Vec::new();
+    let mut run: Option<(&str, usize)> = None;
+
+    for (i, w) in route.windows(2).enumerate() {
+        let ((z1, x1, y1), (z2, x2, y2)) = (w[0], w[1]);
+        let dir = if z2 < z1 {
+            "up"
+        } else if z2 > z1 {
+            "down"
+        } else {
+            match ((x2 + 8 - x1) % 8, (y2 + 8 - y1) % 8) {
+                (1, 0) => "east",
+                (7, 0) => "west",
+                (0, 1) => "south",
+                _ => "north",
+            }
+        };
+
+        if z1 != z2 {
+            ret.push(format!(
+                "climb {} to level {} at ({}, {})",
+                dir,
+                z2 + 1,
+                x2,
+                y2
+            ));
+            continue;
+        }
+
+        let n = match run {
+            Some((d, n)) if d == dir => n + 1,
+            _ => 1,
+        };
+        run = Some((dir, n));
+
+        // Emit the run when it ends.
+        let next_dir_same = route.get(i + 2).is_some_and(|&(z3, x3, y3)| {
+            z3 == z2
+                && (x3 + 8 - x2) % 8 == (x2 + 8 - x1) % 8
+                && (y3 + 8 - y2) % 8 == (y2 + 8 - y1) % 8
+        });
+        if !next_dir_same {
+            let blocks = if n == 1 {
+                String::new()
+            } else {
+                format!(" {} blocks", n)
+            };
+            ret.push(format!("go {}{} to ({}, {})", dir, blocks, x2, y2));
+            run = None;
+        }
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged_directions() {
+        let route = [(0, 6, 0), (0, 7, 0), (0, 0, 0), (0, 0, 7), (1, 0, 7)];
+        assert_eq!(
+            directions(&route),
+            vec![
+                "go east 2 blocks to (0, 0)",
+                "go north to (0, 7)",
+                "climb down to level 2 at (0, 7)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Game state from SAVED.GAM.
+
+use std::{fs, path::Path};
+
+use crate::{settlement::FIRST_DUNGEON, DungeonFloor};
+
+#[derive(Copy, Clone, Debug)]
+pub enum Facing {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl From<u8> for Facing {
+    fn from(b: u8) -> Self {
+        match b & 3 {
+            0 => Facing::North,
+            1 => Facing::East,
+            2 => Facing::South,
+            _ => Facing::West,
+        }
+    }
+}
+
+impl Facing {
+    /// Rotate a point in a `size` x `size` square so that the result is
+    /// in a frame where this direction points north.
+    pub fn to_north(self, x: u32, y: u32, size: u32) -> (u32, u32) {
+        let n = size - 1;
+        match self {
+            Facing::North => (x, y),
+            Facing::East => (y, n - x),
+            Facing::South => (n - x, n - y),
+            Facing::West => (n - y, x),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SavedGame {
+    /// Current party location number.
+    pub location: u8,
+    /// Party position. In dungeons z is the level and x, y are the block
+    /// position on the floor.
+    pub z: u8,
+    pub x: u8,
+    pub y: u8,
+    /// Party orientation in a dungeon.
+    pub facing: Facing,
+    /// Graphics type of the current dungeon, 1 is cave, 2 is mine and 3 is
+    /// dungeon.
+    pub graphics_type: u8,
+    /// Whether each dungeon has been unsealed.
+    pub dungeon_open: [bool; 8],
+    /// Room cleared flags for the first seven dungeons, bit n is room n.
+    /// Doom has no flags since you can't leave it.
+    pub cleared_rooms: [u16; 7],
+    /// Current state of the map of the dungeon the party is in.
+    pub dungeon_floors: [DungeonFloor; 8],
+}
+
+impl SavedGame {
+    /// Size of SAVED.GAM up to the last field read, the graphics type at
+    /// 0x105e.
+    const SIZE: usize = 0x105f;
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let data = fs::read(path)
+            .map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+        if data.len() < Self::SIZE {
+            return Err(format!(
+                "{} is too short for a saved game, {} bytes instead of {}",
+                path.display(),
+                data.len(),
+                Self::SIZE
+            ));
+        }
+
+        let mut cleared_rooms = [0; 7];
+        for (i, b) in data[0x33a..0x348].chunks(2).enumerate() {
+            cleared_rooms[i] = u16::from_le_bytes([b[0], b[1]]);
+        }
+
+        let ret = SavedGame {
+            location: data[0x2ed],
+            z: data[0x2ef],
+            x: data[0x2f0],
+            y: data[0x2f1],
+            facing: data[0x105d].into(),
+            graphics_type: data[0x105e],
+            dungeon_open: std::array::from_fn(|i| data[0x32a + i] & 0x80 != 0),
+            cleared_rooms,
+            dungeon_floors: bincode::deserialize(&data[0x3b4..0x5b4]).unwrap(),
+        };
+
+        if ret.dungeon_index().is_some()
+            && (ret.z >= 8 || ret.x >= 8 || ret.y >= 8)
+        {
+            return Err(format!(
+                "{}: party position ({}, {}) on level {} is outside the dungeon",
+                path.display(),
+                ret.x,
+                ret.y,
+                ret.z
+            ));
+        }
+
+        Ok(ret)
+    }
+
+    /// Index of the dungeon the party is in.
+    pub fn dungeon_index(&self) -> Option<usize> {
+        (FIRST_DUNGEON..FIRST_DUNGEON + 8)
+            .contains(&self.location)
+            .then(|| (self.location - FIRST_DUNGEON) as usize)
+    }
+
+    /// Cleared room flags for a dungeon.
+    pub fn cleared_rooms(&self, dungeon_index: usize) -> u16 {
+        self.cleared_rooms.get(dungeon_index).copied().unwrap_or(0)
+    }
+}
@@ -0,0 +1,107 @@
+//! Towns, dwellings, castles and keeps.
+
+use std::fs;
+
+use lazy_static::lazy_static;
+
+use crate::{data_ovl, tile_map::TileMap, U5_PATH};
+
+/// Names of the game locations, indexed by location number.
+///
+/// Location 0 is the surface world, locations 1 to 0x20 are settlements and
+/// 0x21 to 0x28 are dungeons.
+pub const LOCATION_NAMES: [&str; 0x29] = [
+    "Britannia",
+    "Moonglow",
+    "Britain",
+    "Jhelom",
+    "Yew",
+    "Minoc",
+    "Trinsic",
+    "Skara Brae",
+    "New Magincia",
+    "Fogsbane",
+    "Stormcrow",
+    "Greyhaven",
+    "Waveguide",
+    "Iolo's Hut",
+    "Sutek's Hut",
+    "Sin'Vraal's Hut",
+    "Grendel's Hut",
+    "Lord British's Castle",
+    "Palace of Blackthorn",
+    "West Britanny",
+    "North Britanny",
+    "East Britanny",
+    "Paws",
+    "Cove",
+    "Buccaneer's Den",
+    "Ararat",
+    "Bordermarch",
+    "Farthing",
+    "Windemere",
+    "Stonegate",
+    "Lycaeum",
+    "Empath Abbey",
+    "Serpent's Hold",
+    "Deceit",
+    "Despise",
+    "Destard",
+    "Wrong",
+    "Covetous",
+    "Shame",
+    "Hythloth",
+    "Doom",
+];
+
+/// Location number of the first dungeon.
+pub const FIRST_DUNGEON: u8 = 0x21;
+
+/// Data files of settlements, each holding eight consecutive locations.
+const SETTLEMENT_FILES: [&str; 4] =
+    ["TOWNE.DAT", "DWELLING.DAT", "CASTLE.DAT", "KEEP.DAT"];
+
+lazy_static! {
+    pub static ref SETTLEMENTS: Vec<Settlement> = {
+        let mut ret = Vec::new();
+        for (i, file) in SETTLEMENT_FILES.into_iter().enumerate() {
+            let data = fs::read(U5_PATH.join(file)).unwrap();
+            let maps: Vec<&[u8]> = data.chunks(32 * 32).collect();
+            let starts = data_ovl::settlement_map_starts(file);
+
+            for (j, &start) in starts.iter().enumerate() {
+                let location = (i * 8 + j + 1) as u8;
+                let end = starts.get(j + 1).map_or(maps.len(), |&e| e as usize);
+
+                // The basement level is stored first.
+                let z0 = if has_basement(location) { -1 } else { 0 };
+
+                ret.push(Settlement {
+                    location,
+                    name: LOCATION_NAMES[location as usize],
+                    levels: maps[start as usize..end]
+                        .iter()
+                        .enumerate()
+                        .map(|(z, m)| {
+                            (z as i32 + z0, TileMap::new(32, 32, m.to_vec()))
+                        })
+                        .collect(),
+                });
+            }
+        }
+        ret
+    };
+}
+
+fn has_basement(location: u8) -> bool {
+    // Lord British's castle and Blackthorn's palace.
+    matches!(location, 0x11 | 0x12)
+}
+
+pub struct Settlement {
+    pub location: u8,
+    pub name: &'static str,
+    /// Maps of the settlement levels with their z coordinates, basement is
+    /// -1 and ground floor is 0.
+    pub levels: Vec<(i32, TileMap)>,
+}
@@ -0,0 +1,155 @@
+//! Sign texts from SIGNS.DAT.
+
+use std::fs;
+
+use image::{ImageBuffer, Rgb};
+use lazy_static::lazy_static;
+
+use crate::{
+    font::{Font, IBM, RUNES},
+    Color::*,
+    EGA, U5_PATH,
+};
+
+lazy_static! {
+    pub static ref SIGNS: Vec<Sign> = {
+        let data = fs::read(U5_PATH.join("SIGNS.DAT")).unwrap();
+
+        // Offsets to the sign groups of the 0x21 locations, zero for
+        // locations without signs.
+        let mut offsets: Vec<usize> = data[..0x42]
+            .chunks(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+            .filter(|&o| o != 0)
+            .collect();
+        offsets.sort();
+        offsets.push(data.len());
+
+        let mut ret = Vec::new();
+        for span in offsets.windows(2) {
+            // Each group is a sequence of zero-terminated signs with a
+            // four-byte header.
+            let mut group = &data[span[0]..span[1]];
+            while group.len() > 4 {
+                let end = group[4..]
+                    .iter()
+                    .position(|&c| c == 0)
+                    .map_or(group.len(), |p| p + 4);
+                ret.push(Sign {
+                    location: group[0],
+                    z: group[1] as i8 as i32,
+                    x: group[2],
+                    y: group[3],
+                    text: group[4..end].to_vec(),
+                });
+                group = &group[(end + 1).min(group.len())..];
+            }
+        }
+        ret
+    };
+}
+
+#[derive(Clone, Debug)]
+pub struct Sign {
+    /// Location number, 0 is the surface world.
+    pub location: u8,
+    /// Floor of settlement or -1 for basement. On the surface world, 0 is
+    /// Britannia and -1 is the Underworld.
+    pub z: i32,
+    pub x: u8,
+    pub y: u8,
+    /// Characters below 0x80 are runes from RUNES.CH, the rest are Latin
+    /// characters from IBM.CH offset by 0x80.
+    pub text: Vec<u8>,
+}
+
+impl Sign {
+    /// Split text into display lines.
+    ///
+    /// Lines are separated by newlines or lower case letters in the rune
+    /// range.
+    pub fn lines(&self) -> Vec<&[u8]> {
+        self.text
+            .split(|&c| c & 0x7f == b'\n' || c.is_ascii_lowercase())
+            .filter(|line| !line.is_empty())
+            .collect()
+    }
+
+    /// Sign text transliterated to plain Latin characters.
+    pub fn plain_text(&self) -> String {
+        self.lines()
+            .iter()
+            .map(|line| line.iter().map(|&c| (c & 0x7f) as char).collect())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// Draw a numbered marker over the tile of a sign.
+pub fn mark(img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>, x: u32, y: u32, n: usize) {
+    let (x, y) = (x * 16, y * 16);
+    crate::draw_box(img, x, y, 16, 16, EGA[Yellow as usize]);
+    IBM.draw_str(
+        img,
+        x + 1,
+        y + 1,
+        &n.to_string(),
+        EGA[Yellow as usize],
+        Some(EGA[Black as usize]),
+    );
+}
+
+/// Draw a sidebar listing the numbered texts of signs.
+///
+/// Runic text is drawn with runes unless `transliterate` is set.
+pub fn draw_sidebar(
+    signs: &[&Sign],
+    min_height: u32,
+    transliterate: bool,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    const MARGIN: u32 = 8;
+
+    let lines: Vec<Vec<&[u8]>> = signs.iter().map(|s| s.lines()).collect();
+    let columns = lines
+        .iter()
+        .flatten()
+        .map(|line| line.len() as u32)
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    let rows: u32 = lines.iter().map(|l| l.len() as u32 + 2).sum();
+
+    let mut img = ImageBuffer::from_pixel(
+        columns * Font::WIDTH + 2 * MARGIN,
+        (rows * Font::HEIGHT + 2 * MARGIN).max(min_height),
+        EGA[Black as usize],
+    );
+
+    let mut y = MARGIN;
+    for (i, text) in lines.iter().enumerate() {
+        IBM.draw_str(
+            &mut img,
+            MARGIN,
+            y,
+            &format!("{}:", i + 1),
+            EGA[Yellow as usize],
+            None,
+        );
+        y += Font::HEIGHT;
+        for line in text {
+            for (j, &c) in line.iter().enumerate() {
+                let x = MARGIN + j as u32 * Font::WIDTH;
+                let font = if c >= 0x80 || transliterate {
+                    &*IBM
+                } else {
+                    &*RUNES
+                };
+                font.draw_char(&mut img, x, y, c, EGA[White as usize], None);
+            }
+            y += Font::HEIGHT;
+        }
+        y += Font::HEIGHT;
+    }
+
+    img
+}
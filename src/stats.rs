@@ -0,0 +1,151 @@
+//! Counts of dungeon features.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{monsters, Dungeon, DungeonBlock};
+
+#[derive(Serialize)]
+pub struct LevelStats {
+    pub dungeon: String,
+    /// Level number starting from 1.
+    pub level: usize,
+    /// Number of blocks of each kind and subtype.
+    pub blocks: BTreeMap<String, usize>,
+    /// Combat rooms on the level.
+    pub rooms: Vec<RoomStats>,
+}
+
+#[derive(Serialize)]
+pub struct RoomStats {
+    pub index: u8,
+    pub monsters: Vec<MonsterCount>,
+    /// Slots for a monster picked at random when entering the room.
+    pub random_monsters: usize,
+    /// Number of triggers, one for each occupied trigger slot.
+    pub triggers: usize,
+}
+
+#[derive(Serialize)]
+pub struct MonsterCount {
+    pub tile: usize,
+    pub name: String,
+    pub count: usize,
+}
+
+/// Statistics for each level of a dungeon.
+pub fn stats(dungeon: &Dungeon) -> Vec<LevelStats> {
+    let mut ret = Vec::new();
+    for (z, floor) in dungeon.floors.iter().enumerate() {
+        let mut blocks = BTreeMap::new();
+        let mut indices = Vec::new();
+        for &b in floor.0.iter().flatten() {
+            *blocks.entry(b.describe()).or_insert(0) += 1;
+            if let DungeonBlock::Room { index, .. } = b {
+                indices.push(index);
+            }
+        }
+        indices.sort();
+        indices.dedup();
+
+        let rooms = indices
+            .into_iter()
+            .map(|index| {
+                let room = &dungeon.rooms[index as usize];
+                let mut counts = BTreeMap::new();
+                let mut random_monsters = 0;
+                for m in room.monsters().into_values() {
+                    if monsters::is_random(m) {
+                        random_monsters += 1;
+                    } else {
+                        *counts.entry(m).or_insert(0) += 1;
+                    }
+                }
+                let monsters = counts
+                    .into_iter()
+                    .map(|(tile, count)| MonsterCount {
+                        tile,
+                        name: monsters::name(tile),
+                        count,
+                    })
+                    .collect();
+                RoomStats {
+                    index,
+                    monsters,
+                    random_monsters,
+                    triggers: room.triggers().count(),
+                }
+            })
+            .collect();
+
+        ret.push(LevelStats {
+            dungeon: dungeon.name.clone(),
+            level: z + 1,
+            blocks,
+            rooms,
+        });
+    }
+    ret
+}
+
+/// Print statistics as a plain text table.
+pub fn print_table(stats: &[LevelStats]) {
+    for level in stats {
+        println!("{} level {}", level.dungeon, level.level);
+        for (kind, n) in &level.blocks {
+            println!("    {:32} {:3}", kind, n);
+        }
+        println!("    {:32} {:3}", "combat rooms", level.rooms.len());
+        for room in &level.rooms {
+            let monsters = room
+                .monsters
+                .iter()
+                .map(|m| (m.tile, m.count))
+                .chain([(*monsters::RANDOM.start(), room.random_monsters)]);
+            println!(
+                "    room {:2}: {} triggers, {}",
+                room.index,
+                room.triggers,
+                monsters::summary(monsters)
+            );
+        }
+    }
+}
+
+/// Print statistics as CSV with one count per row.
+pub fn print_csv(stats: &[LevelStats]) {
+    println!("dungeon,level,category,item,count");
+    for level in stats {
+        let row = |category: &str, item: &str, n: usize| {
+            println!(
+                "{},{},{},\"{}\",{}",
+                level.dungeon, level.level, category, item, n
+            )
+        };
+        for (kind, &n) in &level.blocks {
+            row("block", kind, n);
+        }
+        row("rooms", "", level.rooms.len());
+        for room in &level.rooms {
+            for m in &room.monsters {
+                row(
+                    "monster",
+                    &format!(
+                        "room {} {} (tile {})",
+                        room.index, m.name, m.tile
+                    ),
+                    m.count,
+                );
+            }
+            if room.random_monsters > 0 {
+                row(
+                    "random monsters",
+                    &format!("room {}", room.index),
+                    room.random_monsters,
+                );
+            }
+            row("triggers", &format!("room {}", room.index), room.triggers);
+        }
+    }
+}
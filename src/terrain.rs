@@ -1,3 +1,5 @@
+use crate::look;
+
 #[rustfmt::skip]
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum Terrain {
@@ -50,9 +52,93 @@ pub enum Terrain {
 }
 use Terrain::*;
 
-impl Into<char> for Terrain {
-    fn into(self) -> char {
-        match self {
+impl Terrain {
+    /// Terrain that a tile description names, such as `DeepWater` for "deep
+    /// water".
+    fn from_description(desc: &str) -> Option<Terrain> {
+        let desc = normalize(desc);
+        if desc.is_empty() {
+            return None;
+        }
+        TERRAIN
+            .iter()
+            .copied()
+            .chain([Chest])
+            .filter(|&t| t != Unknown)
+            .find(|t| normalize(&format!("{:?}", t)) == desc)
+    }
+
+    /// Whether the party can walk on the terrain.
+    pub fn is_passable(self) -> bool {
+        !matches!(
+            self,
+            DeepWater
+                | Water
+                | Mountains
+                | HighPeaks
+                | Rocks
+                | StoneWall
+                | BrickWall
+                | StrangeWall
+                | Crenellations
+                | Wall
+                | Darkness
+        )
+    }
+}
+
+/// Lowercase words without articles or plural endings, "DeepWater" and "the
+/// deep waters" both become "deep water".
+fn normalize(name: &str) -> String {
+    let mut words = String::new();
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && !words.is_empty() {
+            words.push(' ');
+        }
+        if c.is_ascii_alphabetic() || c == ' ' {
+            words.push(c.to_ascii_lowercase());
+        }
+    }
+    let words = words.trim();
+    let words = ["a ", "an ", "the "]
+        .iter()
+        .find_map(|a| words.strip_prefix(a))
+        .unwrap_or(words);
+    words
+        .split_whitespace()
+        .map(|w| w.strip_suffix('s').unwrap_or(w))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Terrain of any of the 512 tiles.
+///
+/// Tiles that `TERRAIN` leaves unknown are classified by their LOOK2.DAT
+/// description if it names a terrain.
+pub fn classify(tile: usize) -> Terrain {
+    match TERRAIN.get(tile) {
+        Some(&t) if t != Unknown => t,
+        _ => Terrain::from_description(look::describe(tile)).unwrap_or(Unknown),
+    }
+}
+
+/// Tiles whose LOOK2.DAT description names a different terrain than their
+/// `TERRAIN` entry, as (tile, entry, described terrain).
+pub fn mismatches() -> Vec<(usize, Terrain, Terrain)> {
+    TERRAIN
+        .iter()
+        .enumerate()
+        .filter(|(_, &t)| t != Unknown)
+        .filter_map(|(i, &t)| {
+            let described = Terrain::from_description(look::describe(i))?;
+            (described != t).then_some((i, t, described))
+        })
+        .collect()
+}
+
+impl From<Terrain> for char {
+    fn from(terrain: Terrain) -> char {
+        match terrain {
             DeepWater => '≋',
             Water => '≈',
             Shoals => '~',
@@ -172,3 +258,15 @@ pub const TERRAIN: [Terrain; 256] = [
     GuildSign, InnSign, ApothecarySign, Unknown, ShipwrightSign,
     GrandfatherClock, GrandfatherClock, Bellows, Bellows, Wall, Darkness,
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn description_names() {
+        assert_eq!(normalize("DeepWater"), "deep water");
+        assert_eq!(normalize("the deep waters"), "deep water");
+        assert_eq!(normalize("a Bookshelf"), normalize("Bookshelf"));
+    }
+}
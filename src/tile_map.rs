@@ -0,0 +1,50 @@
+use image::{ImageBuffer, Rgb};
+
+/// Rectangular map of plain tile indices, like the surface world and
+/// settlement maps.
+#[derive(Clone, Debug)]
+pub struct TileMap {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<u8>,
+}
+
+impl TileMap {
+    pub fn new(width: usize, height: usize, tiles: Vec<u8>) -> Self {
+        assert_eq!(tiles.len(), width * height);
+        TileMap {
+            width,
+            height,
+            tiles,
+        }
+    }
+
+    /// Read a map stored as `height` rows of `stride` bytes where only the
+    /// first `width` bytes of each row are tiles.
+    pub fn from_padded_rows(
+        width: usize,
+        height: usize,
+        stride: usize,
+        data: &[u8],
+    ) -> Self {
+        let tiles = data
+            .chunks(stride)
+            .take(height)
+            .flat_map(|row| row[..width].iter().copied())
+            .collect();
+        TileMap::new(width, height, tiles)
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.tiles[y * self.width + x]
+    }
+
+    pub fn draw(
+        &self,
+        tiles: &[[[Rgb<u8>; 16]; 16]; 512],
+    ) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        crate::draw_tiles(tiles, self.width, self.height, |x, y| {
+            self.get(x, y) as usize
+        })
+    }
+}
@@ -0,0 +1,732 @@
+//! Dungeon levels as Tiled maps for editing.
+//!
+//! A level is an 88x88 tile map of the original 8x8 block grid, with the
+//! dungeon name and 1-based level number in the map properties `dungeon`
+//! and `level`.
+//!
+//! * The `blocks` object layer has a rectangle over each block with the
+//!   block's DUNGEON.DAT byte in its `block` property.
+//! * The `area` tile layer has the tiles of the level. Tiles in combat room
+//!   blocks are the room maps, the rest are only there for show.
+//! * The `monsters` tile layer has the monsters of the combat rooms.
+//! * The `triggers` object layer has the triggers of the combat rooms. The
+//!   `tile` property is the tile the trigger changes things into and
+//!   `changes` lists the one or two changed tiles as space separated `x,y`
+//!   pairs in room coordinates.
+//!
+//! Maps are exported as TMJ files, both TMJ and TMX files can be imported.
+//! TMX layer data must be CSV or plain XML.
+
+use std::collections::HashMap;
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::{
+    combat_map::{CombatMap, Trigger},
+    slug, Dungeon, DungeonBlock, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+/// Map size in tiles.
+const SIZE: u32 = 8 * 11;
+
+/// Tile size in pixels.
+const TILE: u32 = 16;
+
+/// Bits of a gid used for tile flipping.
+const FLIP_FLAGS: u32 = 0xe000_0000;
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Map {
+    #[serde(rename = "type")]
+    kind: String,
+    version: String,
+    orientation: String,
+    renderorder: String,
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    infinite: bool,
+    nextlayerid: u32,
+    nextobjectid: u32,
+    properties: Vec<Property>,
+    tilesets: Vec<Tileset>,
+    layers: Vec<Layer>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Tileset {
+    firstgid: u32,
+    name: String,
+    image: String,
+    imagewidth: u32,
+    imageheight: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    tilecount: u32,
+    columns: u32,
+    margin: u32,
+    spacing: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Layer {
+    TileLayer(TileLayer),
+    ObjectGroup(ObjectGroup),
+    /// Image layers and layer groups aren't used.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct TileLayer {
+    id: u32,
+    name: String,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    opacity: f32,
+    visible: bool,
+    #[serde(deserialize_with = "layer_data")]
+    data: Vec<u32>,
+}
+
+/// Tile layer data of a TMJ file, which has to be a plain array.
+fn layer_data<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u32>, D::Error> {
+    match Value::deserialize(d)? {
+        Value::String(_) => Err(D::Error::custom(
+            "only CSV/array layer data is supported, save the map with CSV \
+             layer data",
+        )),
+        v => serde_json::from_value(v).map_err(D::Error::custom),
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ObjectGroup {
+    id: u32,
+    name: String,
+    x: i32,
+    y: i32,
+    opacity: f32,
+    visible: bool,
+    draworder: String,
+    objects: Vec<Object>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Object {
+    id: u32,
+    name: String,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    rotation: f64,
+    visible: bool,
+    properties: Vec<Property>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Property {
+    name: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+    value: Value,
+}
+
+impl Property {
+    fn int(name: &str, value: i64) -> Self {
+        Property {
+            name: name.into(),
+            kind: "int".into(),
+            value: value.into(),
+        }
+    }
+
+    fn string(name: &str, value: String) -> Self {
+        Property {
+            name: name.into(),
+            kind: "string".into(),
+            value: value.into(),
+        }
+    }
+}
+
+/// Value of a named property.
+fn property<'a>(properties: &'a [Property], name: &str) -> Option<&'a Value> {
+    properties.iter().find(|p| p.name == name).map(|p| &p.value)
+}
+
+impl Map {
+    fn tile_layer(&self, name: &str) -> Option<&TileLayer> {
+        self.layers.iter().find_map(|l| match l {
+            Layer::TileLayer(l) if l.name == name => Some(l),
+            _ => None,
+        })
+    }
+
+    fn objects(&self, name: &str) -> &[Object] {
+        self.layers
+            .iter()
+            .find_map(|l| match l {
+                Layer::ObjectGroup(l) if l.name == name => Some(&l.objects[..]),
+                _ => None,
+            })
+            .unwrap_or(&[])
+    }
+
+    /// Read a map from the contents of a TMX file.
+    pub fn from_tmx(text: &str) -> Result<Map, String> {
+        let doc =
+            roxmltree::Document::parse(text).map_err(|e| e.to_string())?;
+        let root = doc.root_element();
+        if root.tag_name().name() != "map" {
+            return Err("Not a TMX map".into());
+        }
+
+        let mut ret = Map {
+            width: attr(root, "width")?,
+            height: attr(root, "height")?,
+            tilewidth: attr(root, "tilewidth")?,
+            tileheight: attr(root, "tileheight")?,
+            properties: tmx_properties(root)?,
+            ..Default::default()
+        };
+
+        for node in root.children().filter(|n| n.is_element()) {
+            match node.tag_name().name() {
+                "tileset" => ret.tilesets.push(Tileset {
+                    firstgid: attr(node, "firstgid")?,
+                    ..Default::default()
+                }),
+                "layer" => {
+                    let data = node
+                        .children()
+                        .find(|n| n.has_tag_name("data"))
+                        .ok_or("Layer without data")?;
+                    let data = match data.attribute("encoding") {
+                        Some("csv") => data
+                            .text()
+                            .unwrap_or("")
+                            .split(',')
+                            .map(|s| s.trim().parse::<u32>())
+                            .collect::<Result<_, _>>()
+                            .map_err(|e| e.to_string())?,
+                        None => data
+                            .children()
+                            .filter(|n| n.has_tag_name("tile"))
+                            .map(|n| attr(n, "gid").or(Ok(0)))
+                            .collect::<Result<_, String>>()?,
+                        Some(encoding) => {
+                            return Err(format!(
+                                "Unsupported layer encoding {}, save the \
+                                 map with CSV layer data",
+                                encoding
+                            ))
+                        }
+                    };
+                    ret.layers.push(Layer::TileLayer(TileLayer {
+                        name: node.attribute("name").unwrap_or("").into(),
+                        width: attr(node, "width")?,
+                        height: attr(node, "height")?,
+                        data,
+                        ..Default::default()
+                    }));
+                }
+                "objectgroup" => {
+                    let mut objects = Vec::new();
+                    for n in
+                        node.children().filter(|n| n.has_tag_name("object"))
+                    {
+                        objects.push(Object {
+                            id: attr(n, "id").unwrap_or(0),
+                            name: n.attribute("name").unwrap_or("").into(),
+                            x: attr(n, "x")?,
+                            y: attr(n, "y")?,
+                            width: attr(n, "width").unwrap_or(0.0),
+                            height: attr(n, "height").unwrap_or(0.0),
+                            properties: tmx_properties(n)?,
+                            ..Default::default()
+                        });
+                    }
+                    ret.layers.push(Layer::ObjectGroup(ObjectGroup {
+                        name: node.attribute("name").unwrap_or("").into(),
+                        objects,
+                        ..Default::default()
+                    }));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(ret)
+    }
+}
+
+/// Parse a numeric attribute of a TMX element.
+fn attr<T: std::str::FromStr>(
+    node: roxmltree::Node,
+    name: &str,
+) -> Result<T, String> {
+    node.attribute(name)
+        .and_then(|a| a.parse().ok())
+        .ok_or_else(|| {
+            format!("Bad or missing {} in <{}>", name, node.tag_name().name())
+        })
+}
+
+/// Custom properties of a TMX element.
+fn tmx_properties(node: roxmltree::Node) -> Result<Vec<Property>, String> {
+    let mut ret = Vec::new();
+    let props = node.children().filter(|n| n.has_tag_name("properties"));
+    for p in props.flat_map(|n| n.children()).filter(|n| n.is_element()) {
+        let name = p.attribute("name").unwrap_or("").to_string();
+        let kind = p.attribute("type").unwrap_or("string").to_string();
+        let text = p.attribute("value").or(p.text()).unwrap_or("");
+        let value = if kind == "int" {
+            text.parse::<i64>()
+                .map_err(|_| format!("Bad int property {}", name))?
+                .into()
+        } else {
+            text.into()
+        };
+        ret.push(Property { name, kind, value });
+    }
+    Ok(ret)
+}
+
+/// Export a dungeon level as a Tiled map.
+pub fn export(dungeon: &Dungeon, z: i32) -> Map {
+    let mut area = Vec::new();
+    let mut monsters = Vec::new();
+    for y in 0..SIZE as i32 {
+        for x in 0..SIZE as i32 {
+            area.push(dungeon.tile(x, y, z).tile as u32 + 1);
+            let monster = match dungeon.floors[z as usize].0[y as usize / 11]
+                [x as usize / 11]
+            {
+                DungeonBlock::Room { index, .. } => dungeon.rooms
+                    [index as usize]
+                    .monster_at([(x % 11) as u8, (y % 11) as u8])
+                    .map_or(0, |m| m as u32 + 1),
+                _ => 0,
+            };
+            monsters.push(monster);
+        }
+    }
+
+    let mut next_id = 1;
+    let mut blocks = Vec::new();
+    let mut triggers = Vec::new();
+    for (v, row) in dungeon.floors[z as usize].0.iter().enumerate() {
+        for (u, &block) in row.iter().enumerate() {
+            let (x0, y0) = (
+                u as f64 * SCREEN_WIDTH as f64,
+                v as f64 * SCREEN_HEIGHT as f64,
+            );
+            blocks.push(Object {
+                id: next_id,
+                name: format!("{:?}", block),
+                x: x0,
+                y: y0,
+                width: SCREEN_WIDTH as f64,
+                height: SCREEN_HEIGHT as f64,
+                visible: true,
+                properties: vec![Property::int(
+                    "block",
+                    u8::from(block).into(),
+                )],
+                ..Default::default()
+            });
+            next_id += 1;
+
+            let DungeonBlock::Room { index, .. } = block else {
+                continue;
+            };
+            let mut room_triggers: Vec<_> =
+                dungeon.rooms[index as usize].triggers().collect();
+            room_triggers.sort_by_key(|t| t.pos);
+            for t in room_triggers {
+                let p = t.pos;
+                let mut changes = t.changes.to_vec();
+                changes.dedup();
+                triggers.push(Object {
+                    id: next_id,
+                    name: "trigger".into(),
+                    x: x0 + (p[0] as u32 * TILE) as f64,
+                    y: y0 + (p[1] as u32 * TILE) as f64,
+                    width: TILE as f64,
+                    height: TILE as f64,
+                    visible: true,
+                    properties: vec![
+                        Property::int("tile", t.tile as i64),
+                        Property::string(
+                            "changes",
+                            changes
+                                .iter()
+                                .map(|c| format!("{},{}", c[0], c[1]))
+                                .collect::<Vec<_>>()
+                                .join(" "),
+                        ),
+                    ],
+                    ..Default::default()
+                });
+                next_id += 1;
+            }
+        }
+    }
+
+    let tile_layer = |id, name: &str, data| {
+        Layer::TileLayer(TileLayer {
+            id,
+            name: name.into(),
+            width: SIZE,
+            height: SIZE,
+            opacity: 1.0,
+            visible: true,
+            data,
+            ..Default::default()
+        })
+    };
+    let object_group = |id, name: &str, objects, visible| {
+        Layer::ObjectGroup(ObjectGroup {
+            id,
+            name: name.into(),
+            opacity: 1.0,
+            visible,
+            draworder: "topdown".into(),
+            objects,
+            ..Default::default()
+        })
+    };
+
+    Map {
+        kind: "map".into(),
+        version: "1.10".into(),
+        orientation: "orthogonal".into(),
+        renderorder: "right-down".into(),
+        width: SIZE,
+        height: SIZE,
+        tilewidth: TILE,
+        tileheight: TILE,
+        infinite: false,
+        nextlayerid: 5,
+        nextobjectid: next_id,
+        properties: vec![
+            Property::string("dungeon", dungeon.name.clone()),
+            Property::int("level", z as i64 + 1),
+        ],
+        tilesets: vec![Tileset {
+            firstgid: 1,
+            name: "tiles".into(),
+            image: "tiles.png".into(),
+            imagewidth: 32 * TILE,
+            imageheight: 16 * TILE,
+            tilewidth: TILE,
+            tileheight: TILE,
+            tilecount: 512,
+            columns: 32,
+            margin: 0,
+            spacing: 0,
+        }],
+        layers: vec![
+            tile_layer(1, "area", area),
+            tile_layer(2, "monsters", monsters),
+            object_group(3, "blocks", blocks, false),
+            object_group(4, "triggers", triggers, true),
+        ],
+    }
+}
+
+/// Apply an edited Tiled map to the dungeon level it was exported from.
+///
+/// Nothing is changed if the map has errors.
+pub fn import(map: &Map, dungeons: &mut [Dungeon]) -> Result<(), Vec<String>> {
+    let name = property(&map.properties, "dungeon")
+        .and_then(|v| v.as_str())
+        .ok_or(vec!["Map has no dungeon property".to_string()])?;
+    let Some(dungeon) =
+        dungeons.iter_mut().find(|d| slug(&d.name) == slug(name))
+    else {
+        return Err(vec![format!("Unknown dungeon {}", name)]);
+    };
+    let z = property(&map.properties, "level")
+        .and_then(|v| v.as_i64())
+        .filter(|z| (1..=8).contains(z))
+        .ok_or(vec!["Map has no level property between 1 and 8".to_string()])?
+        as usize
+        - 1;
+
+    if (map.width, map.height) != (SIZE, SIZE) {
+        return Err(vec![format!(
+            "Map is {}x{} tiles instead of {}x{}",
+            map.width, map.height, SIZE, SIZE
+        )]);
+    }
+
+    let mut errors = Vec::new();
+    let firstgid = map.tilesets.first().map_or(1, |t| t.firstgid);
+
+    // Tile layer contents as tile numbers.
+    let layer = |name: &str, errors: &mut Vec<String>| {
+        let Some(layer) = map.tile_layer(name) else {
+            errors.push(format!("No {} tile layer", name));
+            return vec![None; (SIZE * SIZE) as usize];
+        };
+        if layer.data.len() != (SIZE * SIZE) as usize {
+            errors.push(format!("{} layer has the wrong size", name));
+            return vec![None; (SIZE * SIZE) as usize];
+        }
+        layer
+            .data
+            .iter()
+            .map(|&gid| {
+                let gid = gid & !FLIP_FLAGS;
+                (gid >= firstgid).then(|| (gid - firstgid) as usize)
+            })
+            .collect()
+    };
+    let area = layer("area", &mut errors);
+    let monsters = layer("monsters", &mut errors);
+
+    // Block grid position of an object.
+    let block_of = |o: &Object| {
+        let (x, y) = (o.x + o.width / 2.0, o.y + o.height / 2.0);
+        let (u, v) = (
+            (x / SCREEN_WIDTH as f64).floor(),
+            (y / SCREEN_HEIGHT as f64).floor(),
+        );
+        ((0.0..8.0).contains(&u) && (0.0..8.0).contains(&v))
+            .then_some((u as usize, v as usize))
+    };
+
+    let mut floor = dungeon.floors[z];
+    for o in map.objects("blocks") {
+        let Some((u, v)) = block_of(o) else {
+            errors.push(format!("Block object {} is outside the map", o.id));
+            continue;
+        };
+        let Some(b) = property(&o.properties, "block")
+            .and_then(|v| v.as_u64())
+            .filter(|&b| b <= 0xff)
+        else {
+            errors.push(format!("Block ({}, {}) has no valid value", u, v));
+            continue;
+        };
+        let block = DungeonBlock::from(b as u8);
+        if let DungeonBlock::Unknown(b) = block {
+            eprintln!(
+                "Warning: block ({}, {}) has unknown value {:#04x}",
+                u, v, b
+            );
+        }
+        floor.0[v][u] = block;
+    }
+
+    // Rooms built from the map, with the block each was first seen in.
+    let mut rooms: HashMap<u8, (CombatMap, (usize, usize))> = HashMap::new();
+    for (v, row) in floor.0.iter().enumerate() {
+        for (u, &block) in row.iter().enumerate() {
+            let at =
+                |x: usize, y: usize| (v * 11 + y) * SIZE as usize + u * 11 + x;
+
+            let DungeonBlock::Room { index, .. } = block else {
+                for y in 0..11 {
+                    for x in 0..11 {
+                        if monsters[at(x, y)].is_some() {
+                            errors.push(format!(
+                                "Monster outside a combat room in block ({}, {})",
+                                u, v
+                            ));
+                        }
+                    }
+                }
+                continue;
+            };
+
+            let mut room = dungeon.rooms[index as usize].clone();
+            let mut room_monsters = Vec::new();
+            let mut room_triggers = Vec::new();
+            for y in 0..11 {
+                for x in 0..11 {
+                    match area[at(x, y)] {
+                        Some(t) if t < 256 => room.area[y][x] = t as u8,
+                        Some(t) => errors.push(format!(
+                            "Room {} at ({}, {}) has non-terrain tile {}",
+                            index, x, y, t
+                        )),
+                        None => errors.push(format!(
+                            "Room {} at ({}, {}) has no tile",
+                            index, x, y
+                        )),
+                    }
+                    if let Some(m) = monsters[at(x, y)] {
+                        room_monsters.push(([x as u8, y as u8], m));
+                    }
+                }
+            }
+
+            for o in map.objects("triggers") {
+                if block_of(o) != Some((u, v)) {
+                    continue;
+                }
+                let pos = [
+                    ((o.x + o.width / 2.0) / TILE as f64) as usize % 11,
+                    ((o.y + o.height / 2.0) / TILE as f64) as usize % 11,
+                ];
+                match trigger(o) {
+                    Ok(mut t) => {
+                        t.pos = [pos[0] as u8, pos[1] as u8];
+                        room_triggers.push(t);
+                    }
+                    Err(e) => errors.push(format!(
+                        "Trigger at ({}, {}) in room {}: {}",
+                        pos[0], pos[1], index, e
+                    )),
+                }
+            }
+
+            if let Err(e) = room
+                .set_monsters(room_monsters)
+                .and_then(|_| room.set_triggers(room_triggers))
+                .and_then(|_| room.to_bytes())
+            {
+                errors.push(format!("Room {}: {}", index, e));
+            }
+
+            match rooms.get(&index) {
+                Some((other, (u2, v2))) => {
+                    if *other != room {
+                        errors.push(format!(
+                            "Room {} differs between blocks ({}, {}) and ({}, {})",
+                            index, u2, v2, u, v
+                        ));
+                    }
+                }
+                None => {
+                    rooms.insert(index, (room, (u, v)));
+                }
+            }
+        }
+    }
+
+    for o in map.objects("triggers") {
+        let in_room = block_of(o).is_some_and(|(u, v)| {
+            matches!(floor.0[v][u], DungeonBlock::Room { .. })
+        });
+        if !in_room {
+            errors.push(format!(
+                "Trigger object {} is outside combat rooms",
+                o.id
+            ));
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    dungeon.floors[z] = floor;
+    for (index, (room, _)) in rooms {
+        dungeon.rooms[index as usize] = room;
+    }
+    Ok(())
+}
+
+/// Trigger of an object, without its position.
+fn trigger(o: &Object) -> Result<Trigger, String> {
+    let tile = property(&o.properties, "tile")
+        .and_then(|v| v.as_u64())
+        .filter(|t| (1..256).contains(t))
+        .ok_or("no tile between 1 and 255")? as u8;
+    let changes = property(&o.properties, "changes")
+        .and_then(|v| v.as_str())
+        .ok_or("no changes")?;
+
+    let mut ret = Vec::new();
+    for pair in changes.split_whitespace() {
+        let p = pair
+            .split_once(',')
+            .and_then(|(x, y)| {
+                Some([x.parse::<u8>().ok()?, y.parse::<u8>().ok()?])
+            })
+            .filter(|p| p[0] < 11 && p[1] < 11)
+            .ok_or_else(|| format!("bad change position {}", pair))?;
+        ret.push(p);
+    }
+    let changes = match ret[..] {
+        [a] => [a, a],
+        [a, b] => [a, b],
+        _ => return Err("must change one or two tiles".into()),
+    };
+    Ok(Trigger {
+        tile,
+        changes,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DungeonFloor, DungeonKind};
+
+    #[test]
+    fn round_trip() {
+        let mut floor = DungeonFloor([[DungeonBlock::Corridor; 8]; 8]);
+        floor.0[2][3] = DungeonBlock::Room {
+            index: 5,
+            cleared: false,
+        };
+        let mut room = CombatMap::default();
+        room.area[4][6] = 0x4c;
+        room.set_monsters([([1, 2], 300)]).unwrap();
+        room.set_triggers([Trigger {
+            tile: 0x4e,
+            pos: [3, 3],
+            changes: [[4, 4], [4, 4]],
+        }])
+        .unwrap();
+
+        let dungeon = Dungeon {
+            name: "Deceit".into(),
+            kind: DungeonKind::Prison,
+            entrance: None,
+            floors: vec![floor; 8],
+            rooms: vec![room; 16],
+            cleared_rooms: 0,
+            cbt_tail: Vec::new(),
+        };
+        let json = serde_json::to_string(&export(&dungeon, 1)).unwrap();
+
+        let mut dungeons = vec![dungeon.clone()];
+        dungeons[0].floors[1].0[2][3] = DungeonBlock::Corridor;
+        dungeons[0].rooms[5] = CombatMap::default();
+        import(&serde_json::from_str(&json).unwrap(), &mut dungeons).unwrap();
+
+        let imported = &dungeons[0];
+        assert_eq!(imported.floors[1].0, dungeon.floors[1].0);
+        assert_eq!(imported.rooms[5], dungeon.rooms[5]);
+    }
+
+    #[test]
+    fn encoded_layers() {
+        let json = r#"{"layers": [{"type": "tilelayer", "name": "area",
+            "encoding": "base64", "compression": "zlib", "data": "eJw="}]}"#;
+        let e = serde_json::from_str::<Map>(json).err().unwrap().to_string();
+        assert!(
+            e.contains("only CSV/array layer data is supported"),
+            "{}",
+            e
+        );
+    }
+}